@@ -0,0 +1,97 @@
+//! Compares `minigrep::search`, which scans the whole buffer for the query,
+//! with two ways of searching one line at a time: the original
+//! `lines()` and `contains` loop, and the literal matcher that `search` used
+//! before it scanned whole buffers.
+//!
+//! Run with `cargo bench`. The input is about 64 MiB of generated log lines.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use minigrep::{Config, Searcher};
+
+const RUNS: usize = 5;
+
+fn main() {
+    let contents = log(64 << 20);
+    let megabytes = contents.len() as f64 / (1 << 20) as f64;
+    let speed = |taken: Duration| megabytes / taken.as_secs_f64();
+
+    // A query that's rare, one on about one line in ten, and one on every
+    // line.
+    for query in ["segfault", "ERROR", "request"] {
+        let original = time(|| original(query, &contents).len());
+        let matcher = time(|| line_by_line(query, &contents));
+        let buffer = time(|| minigrep::search(query, &contents).len());
+
+        println!(
+            "{query:>10}: original {:>7.1} MiB/s, matcher {:>7.1} MiB/s, \
+             whole buffer {:>7.1} MiB/s ({:.1}x the original, {:.1}x the matcher)",
+            speed(original),
+            speed(matcher),
+            speed(buffer),
+            original.as_secs_f64() / buffer.as_secs_f64(),
+            matcher.as_secs_f64() / buffer.as_secs_f64(),
+        );
+    }
+}
+
+/// The search as the book first wrote it, which only finds the lines.
+fn original<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    let mut results = Vec::new();
+
+    for line in contents.lines() {
+        if line.contains(query) {
+            results.push(line);
+        }
+    }
+
+    results
+}
+
+/// Finds the same matches as `minigrep::search`, by running the literal
+/// matcher over each line in turn, as a `Searcher` does and as `search` did
+/// before it scanned whole buffers.
+fn line_by_line(query: &str, contents: &str) -> usize {
+    let config = Config::builder().pattern(query).build().unwrap();
+    let searcher = Searcher::new(&config).unwrap();
+    searcher.search(contents).collect::<Vec<_>>().len()
+}
+
+/// The fastest of several runs of `search`.
+fn time(search: impl Fn() -> usize) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let started = Instant::now();
+            black_box(search());
+            started.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+/// Generates at least `len` bytes of plausible log lines.
+fn log(len: usize) -> String {
+    let mut contents = String::with_capacity(len + 128);
+    let mut line = 0u64;
+
+    while contents.len() < len {
+        let level = if line % 10 == 3 { "ERROR" } else { "INFO" };
+        let event = if line % 100_000 == 99_999 {
+            "worker crashed with segfault"
+        } else {
+            "handled request in"
+        };
+        contents.push_str(&format!(
+            "2024-05-{:02} 12:{:02}:{:02} {level} [worker-{}] {event} {}ms\n",
+            line % 28 + 1,
+            line / 60 % 60,
+            line % 60,
+            line % 16,
+            line * 7 % 1000,
+        ));
+        line += 1;
+    }
+
+    contents
+}
@@ -1,18 +1,31 @@
 use std::{env, process};
 
-use minigrep::Config;
+use minigrep::{Config, ConfigError};
 
 // run with cargo run -q --bin minigrep -- to poem.txt
 fn main() {
     let args: Vec<String> = env::args().collect();
 
     let config = Config::build(&args).unwrap_or_else(|err| {
-        eprintln!("Problem parsing arguments: {err}");
-        process::exit(1);
+        match err {
+            ConfigError::Help => print!("{}", minigrep::USAGE),
+            ConfigError::Version => println!("minigrep {}", minigrep::VERSION),
+            err => {
+                eprintln!("Problem parsing arguments: {err}");
+                eprintln!("Try 'minigrep --help' for more information.");
+                process::exit(2);
+            }
+        }
+        process::exit(0);
     });
 
-    if let Err(err) = minigrep::run(config) {
-        eprintln!("Application error: {err}");
-        process::exit(1);
+    // Exit like grep: 0 if a line was selected, 1 if not, 2 on error.
+    match minigrep::run(config) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("Application error: {err}");
+            process::exit(2);
+        }
     }
 }
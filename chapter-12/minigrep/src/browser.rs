@@ -0,0 +1,388 @@
+//! The state of the `--interactive` result browser, kept apart from the
+//! terminal so that it can be tested without one.
+//!
+//! The browser starts with every line the search selected. What's typed is a
+//! filter within those results, not a new search: it narrows them down to
+//! the lines that also contain it, ignoring case unless it has an uppercase
+//! letter, as with `--smart-case`. Lines the search didn't select never
+//! appear, whatever is typed.
+
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::fold;
+
+/// A file with at least one selected line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct File {
+    pub path: PathBuf,
+    /// Every line of the file, for the preview.
+    pub lines: Vec<String>,
+    pub hits: Vec<Hit>,
+}
+
+/// A selected line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    /// Where the line is in its file's `lines`, counting from 0.
+    pub index: usize,
+    pub submatches: Vec<Range<usize>>,
+}
+
+/// A key press, as far as the browser is concerned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Char(char),
+    Backspace,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Enter,
+    Escape,
+}
+
+/// What should happen after a key press.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Continue,
+    Quit,
+    /// Quit, printing the `path:line` of the selected hit.
+    Open(String),
+}
+
+/// A line of the list of hits.
+#[derive(Debug, PartialEq)]
+pub enum Row<'b> {
+    /// The path that the hits below it are in.
+    File(&'b File),
+    Hit {
+        line_number: usize,
+        text: &'b str,
+        submatches: &'b [Range<usize>],
+        selected: bool,
+    },
+}
+
+/// A line of the preview of the selected hit's file.
+#[derive(Debug, PartialEq)]
+pub struct PreviewLine<'b> {
+    pub line_number: usize,
+    pub text: &'b str,
+    /// Whether this is the selected hit.
+    pub selected: bool,
+}
+
+pub struct Browser {
+    files: Vec<File>,
+    filter: String,
+    /// The hits containing `filter`, as indexes into `files` and their
+    /// `hits`.
+    visible: Vec<(usize, usize)>,
+    /// Which of `visible` is selected.
+    selected: usize,
+    /// How many hits Page Up and Page Down move by.
+    page: usize,
+}
+
+impl Browser {
+    pub fn new(files: Vec<File>) -> Browser {
+        let mut browser = Browser {
+            files,
+            filter: String::new(),
+            visible: Vec::new(),
+            selected: 0,
+            page: 10,
+        };
+        browser.apply_filter();
+        browser
+    }
+
+    /// What has been typed so far.
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    /// How many hits are showing, out of how many there are.
+    pub fn counts(&self) -> (usize, usize) {
+        let total = self.files.iter().map(|file| file.hits.len()).sum();
+        (self.visible.len(), total)
+    }
+
+    /// Sets how far Page Up and Page Down move, which should be the height
+    /// of the list.
+    pub fn set_page(&mut self, page: usize) {
+        self.page = page.max(1);
+    }
+
+    pub fn handle(&mut self, key: Key) -> Action {
+        let last = self.visible.len().saturating_sub(1);
+
+        match key {
+            Key::Char(c) => {
+                self.filter.push(c);
+                self.apply_filter();
+            }
+            Key::Backspace => {
+                if self.filter.pop().is_some() {
+                    self.apply_filter();
+                }
+            }
+            Key::Up => self.selected = self.selected.saturating_sub(1),
+            Key::Down => self.selected = (self.selected + 1).min(last),
+            Key::PageUp => self.selected = self.selected.saturating_sub(self.page),
+            Key::PageDown => self.selected = (self.selected + self.page).min(last),
+            Key::Enter => {
+                if let Some(location) = self.location() {
+                    return Action::Open(location);
+                }
+            }
+            Key::Escape => return Action::Quit,
+        }
+
+        Action::Continue
+    }
+
+    /// The `path:line` of the selected hit.
+    pub fn location(&self) -> Option<String> {
+        let (file, hit) = self.current()?;
+        Some(format!("{}:{}", file.path.display(), hit.index + 1))
+    }
+
+    /// Returns the rows of the list that fit in `height`, scrolled so that
+    /// the selected hit is in the middle where possible.
+    pub fn rows(&self, height: usize) -> Vec<Row<'_>> {
+        let mut rows = Vec::new();
+        let mut selected_row = 0;
+        let mut last_file = None;
+
+        for (position, &(file_index, hit_index)) in self.visible.iter().enumerate() {
+            let file = &self.files[file_index];
+            if last_file != Some(file_index) {
+                rows.push(Row::File(file));
+                last_file = Some(file_index);
+            }
+
+            let hit = &file.hits[hit_index];
+            let selected = position == self.selected;
+            if selected {
+                selected_row = rows.len();
+            }
+            rows.push(Row::Hit {
+                line_number: hit.index + 1,
+                text: &file.lines[hit.index],
+                submatches: &hit.submatches,
+                selected,
+            });
+        }
+
+        let start = selected_row
+            .saturating_sub(height / 2)
+            .min(rows.len().saturating_sub(height));
+        rows.into_iter().skip(start).take(height).collect()
+    }
+
+    /// Returns the lines around the selected hit that fit in `height`.
+    pub fn preview(&self, height: usize) -> Vec<PreviewLine<'_>> {
+        let Some((file, hit)) = self.current() else {
+            return Vec::new();
+        };
+
+        let start = hit
+            .index
+            .saturating_sub(height / 2)
+            .min(file.lines.len().saturating_sub(height));
+        file.lines
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(height)
+            .map(|(index, text)| PreviewLine {
+                line_number: index + 1,
+                text,
+                selected: index == hit.index,
+            })
+            .collect()
+    }
+
+    /// The file being previewed.
+    pub fn previewed(&self) -> Option<&File> {
+        self.current().map(|(file, _)| file)
+    }
+
+    fn current(&self) -> Option<(&File, &Hit)> {
+        let &(file_index, hit_index) = self.visible.get(self.selected)?;
+        let file = &self.files[file_index];
+        Some((file, &file.hits[hit_index]))
+    }
+
+    /// Recomputes which hits are visible after the filter changes, keeping
+    /// the same hit selected if it still is.
+    fn apply_filter(&mut self) {
+        let current = self.visible.get(self.selected).copied();
+
+        let ignore_case = !self.filter.chars().any(char::is_uppercase);
+        let folded = fold::fold(&self.filter);
+        let contains = |line: &str| {
+            if ignore_case {
+                fold::find_at(&folded, line, 0).is_some()
+            } else {
+                line.contains(&self.filter)
+            }
+        };
+
+        self.visible = self
+            .files
+            .iter()
+            .enumerate()
+            .flat_map(|(file_index, file)| {
+                file.hits
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, hit)| contains(&file.lines[hit.index]))
+                    .map(move |(hit_index, _)| (file_index, hit_index))
+            })
+            .collect();
+
+        self.selected = current
+            .and_then(|current| self.visible.iter().position(|&hit| hit == current))
+            .unwrap_or(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn file(path: &str, contents: &str, hits: &[usize]) -> File {
+        File {
+            path: PathBuf::from(path),
+            lines: contents.lines().map(String::from).collect(),
+            hits: hits
+                .iter()
+                .map(|&index| Hit {
+                    index,
+                    submatches: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn browser() -> Browser {
+        Browser::new(vec![
+            file(
+                "poem.txt",
+                "I'm nobody! Who are you?\nAre you nobody, too?\nThen there's a pair of us - don't tell!",
+                &[0, 1],
+            ),
+            file("frog.txt", "How dreary to be somebody!\nHow public, like a frog", &[0]),
+        ])
+    }
+
+    fn hits<'b>(rows: &[Row<'b>]) -> Vec<(usize, &'b str, bool)> {
+        rows.iter()
+            .filter_map(|row| match *row {
+                Row::Hit {
+                    line_number,
+                    text,
+                    selected,
+                    ..
+                } => Some((line_number, text, selected)),
+                Row::File(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lists_hits_grouped_by_file() {
+        let browser = browser();
+        let rows = browser.rows(10);
+
+        assert_eq!(5, rows.len());
+        assert!(matches!(rows[0], Row::File(file) if file.path == Path::new("poem.txt")));
+        assert!(matches!(rows[3], Row::File(file) if file.path == Path::new("frog.txt")));
+        assert_eq!(
+            vec![
+                (1, "I'm nobody! Who are you?", true),
+                (2, "Are you nobody, too?", false),
+                (1, "How dreary to be somebody!", false),
+            ],
+            hits(&rows)
+        );
+        assert_eq!((3, 3), browser.counts());
+    }
+
+    #[test]
+    fn typing_narrows_the_hits() {
+        let mut browser = browser();
+        browser.handle(Key::Down);
+
+        for c in "nobody".chars() {
+            browser.handle(Key::Char(c));
+        }
+        // The selected line still contains "nobody", so it stays selected.
+        assert_eq!((2, 3), browser.counts());
+        assert_eq!(Some(String::from("poem.txt:2")), browser.location());
+
+        for _ in 0..6 {
+            browser.handle(Key::Backspace);
+        }
+        assert_eq!((3, 3), browser.counts());
+        assert_eq!(Some(String::from("poem.txt:2")), browser.location());
+
+        // With an uppercase letter, case matters.
+        for c in "NOBODY".chars() {
+            browser.handle(Key::Char(c));
+        }
+        assert_eq!((0, 3), browser.counts());
+        assert_eq!(None, browser.location());
+        assert_eq!(Action::Continue, browser.handle(Key::Enter));
+    }
+
+    #[test]
+    fn moving_and_opening() {
+        let mut browser = browser();
+        browser.set_page(2);
+
+        browser.handle(Key::PageDown);
+        assert_eq!(Some(String::from("frog.txt:1")), browser.location());
+        browser.handle(Key::Down);
+        assert_eq!(Some(String::from("frog.txt:1")), browser.location());
+        browser.handle(Key::Up);
+
+        assert_eq!(
+            Action::Open(String::from("poem.txt:2")),
+            browser.handle(Key::Enter)
+        );
+        assert_eq!(Action::Quit, browser.handle(Key::Escape));
+    }
+
+    #[test]
+    fn preview_centres_the_selected_hit() {
+        let mut browser = browser();
+        browser.handle(Key::Down);
+
+        let preview: Vec<(usize, bool)> = browser
+            .preview(2)
+            .iter()
+            .map(|line| (line.line_number, line.selected))
+            .collect();
+        assert_eq!(vec![(1, false), (2, true)], preview);
+
+        let preview = browser.preview(10);
+        assert_eq!(3, preview.len());
+        assert_eq!("Then there's a pair of us - don't tell!", preview[2].text);
+    }
+
+    #[test]
+    fn list_scrolls_to_the_selection() {
+        let mut browser = browser();
+        browser.handle(Key::PageDown);
+
+        let rows = browser.rows(2);
+        assert_eq!(2, rows.len());
+        assert!(matches!(rows[0], Row::File(file) if file.path == Path::new("frog.txt")));
+        assert_eq!(vec![(1, "How dreary to be somebody!", true)], hits(&rows));
+    }
+}
@@ -0,0 +1,1452 @@
+use std::error::Error;
+use std::io::{self, IsTerminal};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::{env, fmt, fs, thread};
+
+use crate::encoding::Encoding;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub const USAGE: &str = "\
+Usage: minigrep [OPTIONS] QUERY [PATH...]
+       minigrep [OPTIONS] -e QUERY... [-f FILE...] [PATH...]
+       minigrep [OPTIONS] --field COLUMN=QUERY [PATH...]
+
+Search for QUERY in each PATH. Directories are searched recursively, skipping
+hidden files and anything listed in .gitignore or .ignore files. With no PATH,
+or when PATH is -, standard input is searched.
+
+Options:
+  -e, --regexp QUERY         search for QUERY; repeat to search for several,
+                             and a line matching any of them is selected
+  -f, --file FILE            search for every line of FILE as a QUERY
+  -E, --regex                treat QUERY as a regular expression
+  -i, --ignore-case          match case-insensitively
+  -s, --case-sensitive       match case-sensitively (overrides IGNORE_CASE)
+  -S, --smart-case           match case-insensitively unless QUERY has an
+                             uppercase letter
+  -U, --multiline            let matches span lines, printing every line a
+                             match touches; searches each file as a whole
+      --fuzzy K              match any part of a line within K typos (inserted,
+                             deleted or changed characters) of QUERY
+  -w, --word-regexp          only match QUERY as a whole word
+  -x, --line-regexp          only match lines that are exactly QUERY
+      --field COLUMN[=QUERY]
+                             treat each file as CSV, whose first line names
+                             the columns, and only search COLUMN; QUERY can be
+                             given here instead of as an argument
+      --jsonpath PATH[=QUERY]
+                             treat each line as JSON and only search the value
+                             at PATH, such as .user.id or .tags.0
+      --select FIELDS        with --field or --jsonpath, print only FIELDS of
+                             each line (comma-separated columns or paths), as
+                             CSV
+  -n, --line-number          prefix each line with its line number
+  -b, --byte-offset          prefix each line with its byte offset
+  -A, --after-context N      print N lines of context after each match
+  -B, --before-context N     print N lines of context before each match
+  -C, --context N            print N lines of context around each match
+  -c, --count                print only a count of matching lines per file
+  -v, --invert-match         select lines that do not match
+  -l, --files-with-matches   print only the names of files with matches
+  -L, --files-without-match  print only the names of files without matches
+  -q, --quiet                print nothing; only set the exit status
+      --replace TEXT         print selected lines with every match replaced
+                             by TEXT; with -E, TEXT may use $1 or ${name} for
+                             a capture group, or ${1} when a letter, digit or
+                             _ comes next, as in ${1}_old
+      --in-place             with --replace, rewrite each file instead of
+                             printing it, keeping the original as FILE.bak,
+                             or FILE.bak.1 and so on if that already exists
+      --color WHEN           highlight matches: auto, always or never
+      --json                 print results as JSON Lines
+      --interactive          browse the results in the terminal: type to
+                             filter within them (this doesn't search again),
+                             and press Enter to print the selected PATH:LINE
+  -g, --glob GLOB            only search files matching GLOB, or not matching
+                             it if it starts with '!' (repeatable)
+  -t, --type TYPE            only search files of TYPE, e.g. rust (repeatable)
+  -T, --type-not TYPE        don't search files of TYPE (repeatable)
+      --hidden               search hidden files and directories
+      --no-ignore            don't skip files matched by .gitignore and .ignore
+  -z, --search-zip           search the decompressed contents of .gz, .zst,
+                             .bz2 and .xz files; needs gzip, zstd, bzip2 or xz
+      --encoding ENC         read files as ENC: utf-8, utf-16le, utf-16be,
+                             latin1 or windows-1252 (default: auto, which
+                             goes by the byte order mark, else utf-8)
+      --mmap                 map large files into memory instead of reading
+                             them; faster, but a file truncated while being
+                             searched makes minigrep crash
+      --follow               after searching PATH, keep watching it and search
+                             lines as they are appended, like tail -f; copes
+                             with the file being truncated or replaced; PATH
+                             must be UTF-8 text
+  -j, --threads N            search N files at once (default: one per CPU)
+  -h, --help                 print this help and exit
+  -V, --version              print the version and exit
+      --                     treat every following argument as positional
+
+Exit status is 0 if any line is selected, 1 if none are, and 2 if an error
+occurred. With -q, a selected line means 0 even if an error occurred.
+
+Environment:
+  MINIGREP_CONFIG            a file of default flags, one per line, with #
+                             for comments (default: ~/.config/minigrep/config;
+                             set it empty to read no file)
+  IGNORE_CASE                match case-insensitively unless -s is given
+  NO_COLOR                   disable --color=auto
+
+Flags in the config file are overridden by environment variables, and both
+are overridden by flags on the command line. A switch that the config file
+turns on can be turned off again with its --no- form: --no-regex,
+--no-multiline, --no-word-regexp, --no-line-regexp, --no-line-number,
+--no-byte-offset, --no-count, --no-invert-match, --no-files-with-matches,
+--no-files-without-match, --no-quiet, --no-in-place, --no-json,
+--no-interactive, --no-hidden, --no-search-zip, --no-mmap and --no-follow, or
+--ignore for --no-ignore. Flags that take a value are overridden by giving
+them again, as with --color never or --encoding auto.
+";
+
+/// Everything that controls a search.
+///
+/// `Config::build` fills this in from the command line; code embedding
+/// minigrep can start from `Config::builder` instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// The queries to search for. A line is selected if any of them match.
+    pub patterns: Vec<String>,
+    /// Files to read more queries from, one per line.
+    pub pattern_files: Vec<PathBuf>,
+    pub file_paths: Vec<PathBuf>,
+    pub ignore_case: bool,
+    /// Ignore case only if the query is all lowercase. Overridden by
+    /// `ignore_case`.
+    pub smart_case: bool,
+    pub regex: bool,
+    /// Let matches span lines.
+    pub multiline: bool,
+    /// Match approximately, allowing up to this many edits.
+    pub fuzzy: Option<usize>,
+    /// Only match whole words.
+    pub word_regexp: bool,
+    /// Only match whole lines. Takes precedence over `word_regexp`.
+    pub line_regexp: bool,
+    /// Read files as CSV and only search this column.
+    pub field: Option<String>,
+    /// Read files as JSON Lines and only search the value at this path.
+    pub jsonpath: Option<String>,
+    /// The columns or paths printed for each line instead of the whole line.
+    pub select: Vec<String>,
+    pub line_number: bool,
+    pub byte_offset: bool,
+    pub before_context: usize,
+    pub after_context: usize,
+    pub count: bool,
+    pub invert_match: bool,
+    pub files_with_matches: bool,
+    pub files_without_match: bool,
+    pub quiet: bool,
+    /// Text to substitute for every match in the lines that are printed.
+    pub replace: Option<String>,
+    /// Write replacements back to the files searched instead of printing.
+    pub in_place: bool,
+    pub color: ColorChoice,
+    pub json: bool,
+    /// Browse the results in a terminal UI instead of printing them.
+    pub interactive: bool,
+    pub globs: Vec<String>,
+    pub types: Vec<String>,
+    pub types_not: Vec<String>,
+    pub hidden: bool,
+    pub no_ignore: bool,
+    /// Decompress files whose extension shows they are compressed.
+    pub search_zip: bool,
+    /// What files are encoded in. If `None`, a byte order mark at the start
+    /// of a file decides, and files without one are read as UTF-8.
+    pub encoding: Option<Encoding>,
+    /// Map large files into memory rather than reading them.
+    pub mmap: bool,
+    /// Keep searching the file as it grows, until interrupted.
+    pub follow: bool,
+    /// How many files to search at once.
+    pub threads: usize,
+}
+
+impl Default for Config {
+    /// A case-sensitive literal search with no patterns, printing plain
+    /// matching lines and using one thread per CPU.
+    fn default() -> Config {
+        Config {
+            patterns: Vec::new(),
+            pattern_files: Vec::new(),
+            file_paths: Vec::new(),
+            ignore_case: false,
+            smart_case: false,
+            regex: false,
+            multiline: false,
+            fuzzy: None,
+            word_regexp: false,
+            line_regexp: false,
+            field: None,
+            jsonpath: None,
+            select: Vec::new(),
+            line_number: false,
+            byte_offset: false,
+            before_context: 0,
+            after_context: 0,
+            count: false,
+            invert_match: false,
+            files_with_matches: false,
+            files_without_match: false,
+            quiet: false,
+            replace: None,
+            in_place: false,
+            color: ColorChoice::Auto,
+            json: false,
+            interactive: false,
+            globs: Vec::new(),
+            types: Vec::new(),
+            types_not: Vec::new(),
+            hidden: false,
+            no_ignore: false,
+            search_zip: false,
+            encoding: None,
+            mmap: false,
+            follow: false,
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        }
+    }
+}
+
+/// When to highlight output with ANSI colours.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    /// Only when writing to a terminal and `NO_COLOR` is not set.
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Decides whether output written to `out` should be coloured.
+    pub fn enabled(self, out: &impl IsTerminal) -> bool {
+        match self {
+            ColorChoice::Auto => {
+                out.is_terminal() && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Why `Config::build` could not produce a `Config`.
+///
+/// `Help` and `Version` are not really failures: they mean the user asked for
+/// information instead of a search, and the caller should print it and exit.
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    MissingQuery,
+    UnknownFlag(String),
+    MissingValue(String),
+    UnexpectedValue(String),
+    InvalidValue {
+        flag: String,
+        value: String,
+    },
+    Conflict(String, String),
+    Requires(String, String),
+    /// The config file couldn't be read, or has a mistake in it.
+    ConfigFile {
+        path: String,
+        message: String,
+    },
+    Help,
+    Version,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::MissingQuery => write!(f, "no query was given"),
+            ConfigError::UnknownFlag(flag) => write!(f, "unknown flag '{flag}'"),
+            ConfigError::MissingValue(flag) => write!(f, "flag '{flag}' needs a value"),
+            ConfigError::UnexpectedValue(flag) => write!(f, "flag '{flag}' does not take a value"),
+            ConfigError::InvalidValue { flag, value } => {
+                write!(f, "invalid value '{value}' for flag '{flag}'")
+            }
+            ConfigError::Conflict(flag, other) => {
+                write!(f, "flag '{flag}' cannot be used with '{other}'")
+            }
+            ConfigError::Requires(flag, other) => {
+                write!(f, "flag '{flag}' can only be used with '{other}'")
+            }
+            ConfigError::ConfigFile { path, message } => {
+                write!(f, "in config file {path}: {message}")
+            }
+            ConfigError::Help => write!(f, "help requested"),
+            ConfigError::Version => write!(f, "version requested"),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Every flag that is a simple switch, as `(short, long)`.
+const FLAGS: &[(Option<char>, &str)] = &[
+    (Some('E'), "regex"),
+    (Some('i'), "ignore-case"),
+    (Some('s'), "case-sensitive"),
+    (Some('S'), "smart-case"),
+    (Some('w'), "word-regexp"),
+    (Some('U'), "multiline"),
+    (Some('x'), "line-regexp"),
+    (Some('n'), "line-number"),
+    (Some('b'), "byte-offset"),
+    (Some('c'), "count"),
+    (Some('v'), "invert-match"),
+    (Some('l'), "files-with-matches"),
+    (Some('L'), "files-without-match"),
+    (Some('q'), "quiet"),
+    (Some('h'), "help"),
+    (Some('V'), "version"),
+    (None, "json"),
+    (None, "interactive"),
+    (None, "hidden"),
+    (None, "no-ignore"),
+    (Some('z'), "search-zip"),
+    (None, "mmap"),
+    (None, "follow"),
+    (None, "in-place"),
+    // These turn off a switch set in the config file.
+    (None, "no-regex"),
+    (None, "no-multiline"),
+    (None, "no-word-regexp"),
+    (None, "no-line-regexp"),
+    (None, "no-line-number"),
+    (None, "no-byte-offset"),
+    (None, "no-count"),
+    (None, "no-invert-match"),
+    (None, "no-files-with-matches"),
+    (None, "no-files-without-match"),
+    (None, "no-quiet"),
+    (None, "no-in-place"),
+    (None, "no-json"),
+    (None, "no-interactive"),
+    (None, "no-hidden"),
+    (None, "ignore"),
+    (None, "no-search-zip"),
+    (None, "no-mmap"),
+    (None, "no-follow"),
+];
+
+/// Every flag that takes a value, as `(short, long)`.
+const OPTIONS: &[(Option<char>, &str)] = &[
+    (Some('e'), "regexp"),
+    (Some('f'), "file"),
+    (Some('A'), "after-context"),
+    (Some('B'), "before-context"),
+    (Some('C'), "context"),
+    (None, "color"),
+    (Some('j'), "threads"),
+    (Some('g'), "glob"),
+    (Some('t'), "type"),
+    (Some('T'), "type-not"),
+    (None, "replace"),
+    (None, "fuzzy"),
+    (None, "encoding"),
+    (None, "field"),
+    (None, "jsonpath"),
+    (None, "select"),
+];
+
+fn by_long(table: &[(Option<char>, &'static str)], long: &str) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(_, name)| *name == long)
+        .map(|(_, name)| *name)
+}
+
+fn by_short(table: &[(Option<char>, &'static str)], short: char) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(c, _)| *c == Some(short))
+        .map(|(_, name)| *name)
+}
+
+impl Config {
+    /// Starts building a `Config` from code rather than from a command line.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder {
+            config: Config::default(),
+        }
+    }
+
+    /// Parses the command line in `args`, whose first element is the program
+    /// name, on top of the defaults in the config file.
+    ///
+    /// Short flags may be combined (`-in` is `-i -n`), and everything after
+    /// `--` is positional. A flag's value may be attached (`-A3`,
+    /// `--context=3`) or given as the next argument.
+    ///
+    /// The config file is the one named by `MINIGREP_CONFIG`, or else
+    /// `~/.config/minigrep/config` if it exists. Each of its lines is a flag
+    /// or a flag's value. Its flags are applied first, then the `IGNORE_CASE`
+    /// environment variable, then the command line, so that later settings
+    /// override earlier ones.
+    pub fn build(args: &[String]) -> Result<Config, ConfigError> {
+        match read_config_file()? {
+            Some((path, contents)) => Config::build_with_defaults(args, &path, &contents),
+            None => Config::build_with_defaults(args, Path::new(""), ""),
+        }
+    }
+
+    /// Like `build`, but without a config file, so that tests don't depend on
+    /// the machine they run on.
+    #[cfg(test)]
+    pub(crate) fn build_without_file(args: &[String]) -> Result<Config, ConfigError> {
+        Config::build_with_defaults(args, Path::new(""), "")
+    }
+
+    /// Like `build`, but with the config file at `path` already read into
+    /// `contents`.
+    fn build_with_defaults(
+        args: &[String],
+        path: &Path,
+        contents: &str,
+    ) -> Result<Config, ConfigError> {
+        let in_file = |message: String| ConfigError::ConfigFile {
+            path: path.display().to_string(),
+            message,
+        };
+
+        let file_args: Vec<String> = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect();
+        let (defaults, stray) = parse(&file_args).map_err(|err| in_file(err.to_string()))?;
+        if let Some(arg) = stray.first() {
+            return Err(in_file(format!("'{arg}' is not a flag")));
+        }
+        if let Some((name, _)) = defaults
+            .iter()
+            .find(|(name, _)| *name == "help" || *name == "version")
+        {
+            return Err(in_file(format!("--{name} can't be set here")));
+        }
+
+        let (flags, positional) = parse(args.get(1..).unwrap_or_default())?;
+
+        if flags.iter().any(|(name, _)| *name == "help") {
+            return Err(ConfigError::Help);
+        }
+        if flags.iter().any(|(name, _)| *name == "version") {
+            return Err(ConfigError::Version);
+        }
+
+        // Once a query has been given with -e, -f or --field NAME=QUERY,
+        // every positional argument is a path.
+        let mut positional = positional.into_iter();
+        let mut patterns = Vec::new();
+        if !flags.iter().any(|&(name, value)| match name {
+            "regexp" | "file" => true,
+            "field" | "jsonpath" => value.is_some_and(|value| value.contains('=')),
+            _ => false,
+        }) {
+            let query = positional.next().ok_or(ConfigError::MissingQuery)?;
+            patterns.push(query.clone());
+        }
+        let file_paths = positional.map(PathBuf::from).collect();
+
+        let mut config = Config {
+            patterns,
+            file_paths,
+            ..Config::default()
+        };
+
+        for (name, value) in defaults {
+            config
+                .set_flag(name, value)
+                .map_err(|err| in_file(err.to_string()))?;
+        }
+        if env::var("IGNORE_CASE").is_ok() {
+            config.set_flag("ignore-case", None)?;
+        }
+        for (name, value) in flags {
+            config.set_flag(name, value)?;
+        }
+
+        config.check()?;
+        Ok(config)
+    }
+
+    /// Checks that the settings make sense together.
+    fn check(&self) -> Result<(), ConfigError> {
+        if self.threads == 0 {
+            return Err(invalid_value("threads", Some("0")));
+        }
+
+        if self.in_place && self.replace.is_none() {
+            return Err(ConfigError::Requires(
+                String::from("--in-place"),
+                String::from("--replace"),
+            ));
+        }
+
+        if self.fuzzy.is_some() {
+            let exact = [
+                (self.regex, "--regex"),
+                (self.word_regexp, "--word-regexp"),
+                (self.line_regexp, "--line-regexp"),
+            ];
+            if let Some((_, other)) = exact.iter().find(|(set, _)| *set) {
+                return Err(ConfigError::Conflict(
+                    String::from("--fuzzy"),
+                    other.to_string(),
+                ));
+            }
+        }
+
+        // These work a line at a time.
+        if self.multiline {
+            let per_line = [
+                (self.line_regexp, "--line-regexp"),
+                (self.fuzzy.is_some(), "--fuzzy"),
+                (self.replace.is_some(), "--replace"),
+            ];
+            if let Some((_, other)) = per_line.iter().find(|(set, _)| *set) {
+                return Err(ConfigError::Conflict(
+                    String::from("--multiline"),
+                    other.to_string(),
+                ));
+            }
+        }
+
+        if self.field.is_some() && self.jsonpath.is_some() {
+            return Err(ConfigError::Conflict(
+                String::from("--field"),
+                String::from("--jsonpath"),
+            ));
+        }
+        if !self.select.is_empty() && self.field.is_none() && self.jsonpath.is_none() {
+            return Err(ConfigError::Requires(
+                String::from("--select"),
+                String::from("--field"),
+            ));
+        }
+        // Fields are found a line at a time, and printed as they are found.
+        if self.field.is_some() || self.jsonpath.is_some() {
+            let flag = if self.field.is_some() {
+                "--field"
+            } else {
+                "--jsonpath"
+            };
+            let whole_line = [
+                (self.multiline, "--multiline"),
+                (self.in_place, "--in-place"),
+                (
+                    !self.select.is_empty() && self.replace.is_some(),
+                    "--replace",
+                ),
+            ];
+            if let Some((_, other)) = whole_line.iter().find(|(set, _)| *set) {
+                return Err(ConfigError::Conflict(flag.to_string(), other.to_string()));
+            }
+        }
+
+        // A followed file never ends, so nothing that waits for the end of
+        // the file can be printed, and it has to be read a line at a time.
+        if self.follow {
+            let whole_file = [
+                (self.count, "--count"),
+                (self.files_without_match, "--files-without-match"),
+                (self.multiline, "--multiline"),
+                (self.in_place, "--in-place"),
+                (self.search_zip, "--search-zip"),
+                (self.encoding.is_some(), "--encoding"),
+            ];
+            if let Some((_, other)) = whole_file.iter().find(|(set, _)| *set) {
+                return Err(ConfigError::Conflict(
+                    String::from("--follow"),
+                    other.to_string(),
+                ));
+            }
+        }
+
+        // Files are edited as UTF-8, and written back the same way.
+        let transformed = [
+            (self.search_zip, "--search-zip"),
+            (self.encoding.is_some(), "--encoding"),
+        ];
+        if let Some((_, other)) = transformed.iter().find(|(set, _)| self.in_place && *set) {
+            return Err(ConfigError::Conflict(
+                String::from("--in-place"),
+                other.to_string(),
+            ));
+        }
+
+        // The browser shows the selected lines as they are, and the
+        // preview gives their context.
+        if self.interactive {
+            let printed = [
+                (self.count, "--count"),
+                (self.files_with_matches, "--files-with-matches"),
+                (self.files_without_match, "--files-without-match"),
+                (self.quiet, "--quiet"),
+                (self.json, "--json"),
+                (self.replace.is_some(), "--replace"),
+                (!self.select.is_empty(), "--select"),
+                (self.in_place, "--in-place"),
+                (self.follow, "--follow"),
+            ];
+            if let Some((_, other)) = printed.iter().find(|(set, _)| *set) {
+                return Err(ConfigError::Conflict(
+                    String::from("--interactive"),
+                    other.to_string(),
+                ));
+            }
+        }
+
+        // Neither of these print lines in the usual way, so they can't be
+        // combined with each other or with flags that print something else.
+        let summaries = [
+            (self.count, "--count"),
+            (self.files_with_matches, "--files-with-matches"),
+            (self.files_without_match, "--files-without-match"),
+            (self.in_place, "--in-place"),
+        ];
+        for (set, flag) in [(self.json, "--json"), (self.in_place, "--in-place")] {
+            let conflict = summaries
+                .iter()
+                .find(|(other_set, other)| set && *other_set && *other != flag);
+            if let Some((_, other)) = conflict {
+                return Err(ConfigError::Conflict(flag.to_string(), other.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_flag(&mut self, name: &str, value: Option<&str>) -> Result<(), ConfigError> {
+        match name {
+            "regexp" => self.patterns.extend(value.map(String::from)),
+            "file" => self.pattern_files.extend(value.map(PathBuf::from)),
+            "regex" => self.regex = true,
+            "no-regex" => self.regex = false,
+            "multiline" => self.multiline = true,
+            "no-multiline" => self.multiline = false,
+            "fuzzy" => self.fuzzy = Some(number(name, value)?),
+            // Whichever of these comes last wins.
+            "ignore-case" => (self.ignore_case, self.smart_case) = (true, false),
+            "case-sensitive" => (self.ignore_case, self.smart_case) = (false, false),
+            "smart-case" => (self.ignore_case, self.smart_case) = (false, true),
+            "word-regexp" => self.word_regexp = true,
+            "no-word-regexp" => self.word_regexp = false,
+            "field" | "jsonpath" => {
+                let value = value.unwrap_or_default();
+                let (field, query) = match value.split_once('=') {
+                    Some((field, query)) => (field, Some(query)),
+                    None => (value, None),
+                };
+                if name == "field" {
+                    self.field = Some(field.to_string());
+                } else {
+                    self.jsonpath = Some(field.to_string());
+                }
+                self.patterns.extend(query.map(String::from));
+            }
+            "select" => self
+                .select
+                .extend(value.unwrap_or_default().split(',').map(String::from)),
+            "line-regexp" => self.line_regexp = true,
+            "no-line-regexp" => self.line_regexp = false,
+            "line-number" => self.line_number = true,
+            "no-line-number" => self.line_number = false,
+            "byte-offset" => self.byte_offset = true,
+            "no-byte-offset" => self.byte_offset = false,
+            "after-context" => self.after_context = number(name, value)?,
+            "before-context" => self.before_context = number(name, value)?,
+            "context" => {
+                self.before_context = number(name, value)?;
+                self.after_context = self.before_context;
+            }
+            "count" => self.count = true,
+            "no-count" => self.count = false,
+            "invert-match" => self.invert_match = true,
+            "no-invert-match" => self.invert_match = false,
+            "files-with-matches" => self.files_with_matches = true,
+            "no-files-with-matches" => self.files_with_matches = false,
+            "files-without-match" => self.files_without_match = true,
+            "no-files-without-match" => self.files_without_match = false,
+            "quiet" => self.quiet = true,
+            "no-quiet" => self.quiet = false,
+            "replace" => self.replace = value.map(String::from),
+            "in-place" => self.in_place = true,
+            "no-in-place" => self.in_place = false,
+            "json" => self.json = true,
+            "no-json" => self.json = false,
+            "interactive" => self.interactive = true,
+            "no-interactive" => self.interactive = false,
+            "glob" => self.globs.extend(value.map(String::from)),
+            "type" => self.types.extend(value.map(String::from)),
+            "type-not" => self.types_not.extend(value.map(String::from)),
+            "hidden" => self.hidden = true,
+            "no-hidden" => self.hidden = false,
+            "no-ignore" => self.no_ignore = true,
+            "ignore" => self.no_ignore = false,
+            "search-zip" => self.search_zip = true,
+            "no-search-zip" => self.search_zip = false,
+            "encoding" => {
+                self.encoding = match value {
+                    Some("auto") => None,
+                    Some(label) => match Encoding::from_label(label) {
+                        Some(encoding) => Some(encoding),
+                        None => return Err(invalid_value(name, value)),
+                    },
+                    None => return Err(invalid_value(name, value)),
+                }
+            }
+            "mmap" => self.mmap = true,
+            "no-mmap" => self.mmap = false,
+            "follow" => self.follow = true,
+            "no-follow" => self.follow = false,
+            "threads" => self.threads = number(name, value)?,
+            "color" => {
+                self.color = match value {
+                    Some("auto") => ColorChoice::Auto,
+                    Some("always") => ColorChoice::Always,
+                    Some("never") => ColorChoice::Never,
+                    _ => return Err(invalid_value(name, value)),
+                }
+            }
+            _ => unreachable!("flag --{name} is handled before the config is built"),
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds a `Config` one setting at a time, starting from the defaults of
+/// `Config::default`.
+///
+/// ```
+/// use minigrep::Config;
+///
+/// let config = Config::builder()
+///     .pattern("frog")
+///     .ignore_case(true)
+///     .line_number(true)
+///     .file_path("poem.txt")
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(vec!["frog"], config.patterns);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// Adds a query to search for.
+    pub fn pattern(mut self, pattern: impl Into<String>) -> ConfigBuilder {
+        self.config.patterns.push(pattern.into());
+        self
+    }
+
+    /// Adds a file of queries, one per line.
+    pub fn pattern_file(mut self, path: impl Into<PathBuf>) -> ConfigBuilder {
+        self.config.pattern_files.push(path.into());
+        self
+    }
+
+    /// Adds a file or directory to search.
+    pub fn file_path(mut self, path: impl Into<PathBuf>) -> ConfigBuilder {
+        self.config.file_paths.push(path.into());
+        self
+    }
+
+    pub fn ignore_case(mut self, yes: bool) -> ConfigBuilder {
+        self.config.ignore_case = yes;
+        self
+    }
+
+    pub fn smart_case(mut self, yes: bool) -> ConfigBuilder {
+        self.config.smart_case = yes;
+        self
+    }
+
+    pub fn regex(mut self, yes: bool) -> ConfigBuilder {
+        self.config.regex = yes;
+        self
+    }
+
+    pub fn multiline(mut self, yes: bool) -> ConfigBuilder {
+        self.config.multiline = yes;
+        self
+    }
+
+    /// Matches approximately, allowing up to `max_distance` edits.
+    pub fn fuzzy(mut self, max_distance: usize) -> ConfigBuilder {
+        self.config.fuzzy = Some(max_distance);
+        self
+    }
+
+    pub fn word_regexp(mut self, yes: bool) -> ConfigBuilder {
+        self.config.word_regexp = yes;
+        self
+    }
+
+    /// Reads files as CSV and only searches the column named `name`.
+    pub fn field(mut self, name: impl Into<String>) -> ConfigBuilder {
+        self.config.field = Some(name.into());
+        self
+    }
+
+    /// Reads files as JSON Lines and only searches the value at `path`.
+    pub fn jsonpath(mut self, path: impl Into<String>) -> ConfigBuilder {
+        self.config.jsonpath = Some(path.into());
+        self
+    }
+
+    /// Prints the column or path `field` of each line, instead of the whole
+    /// line.
+    pub fn select(mut self, field: impl Into<String>) -> ConfigBuilder {
+        self.config.select.push(field.into());
+        self
+    }
+
+    pub fn line_regexp(mut self, yes: bool) -> ConfigBuilder {
+        self.config.line_regexp = yes;
+        self
+    }
+
+    pub fn line_number(mut self, yes: bool) -> ConfigBuilder {
+        self.config.line_number = yes;
+        self
+    }
+
+    pub fn byte_offset(mut self, yes: bool) -> ConfigBuilder {
+        self.config.byte_offset = yes;
+        self
+    }
+
+    pub fn before_context(mut self, lines: usize) -> ConfigBuilder {
+        self.config.before_context = lines;
+        self
+    }
+
+    pub fn after_context(mut self, lines: usize) -> ConfigBuilder {
+        self.config.after_context = lines;
+        self
+    }
+
+    pub fn count(mut self, yes: bool) -> ConfigBuilder {
+        self.config.count = yes;
+        self
+    }
+
+    pub fn invert_match(mut self, yes: bool) -> ConfigBuilder {
+        self.config.invert_match = yes;
+        self
+    }
+
+    pub fn files_with_matches(mut self, yes: bool) -> ConfigBuilder {
+        self.config.files_with_matches = yes;
+        self
+    }
+
+    pub fn files_without_match(mut self, yes: bool) -> ConfigBuilder {
+        self.config.files_without_match = yes;
+        self
+    }
+
+    pub fn quiet(mut self, yes: bool) -> ConfigBuilder {
+        self.config.quiet = yes;
+        self
+    }
+
+    /// Substitutes `text` for every match in the lines that are printed.
+    pub fn replace(mut self, text: impl Into<String>) -> ConfigBuilder {
+        self.config.replace = Some(text.into());
+        self
+    }
+
+    pub fn in_place(mut self, yes: bool) -> ConfigBuilder {
+        self.config.in_place = yes;
+        self
+    }
+
+    pub fn color(mut self, choice: ColorChoice) -> ConfigBuilder {
+        self.config.color = choice;
+        self
+    }
+
+    pub fn json(mut self, yes: bool) -> ConfigBuilder {
+        self.config.json = yes;
+        self
+    }
+
+    pub fn interactive(mut self, yes: bool) -> ConfigBuilder {
+        self.config.interactive = yes;
+        self
+    }
+
+    /// Adds a glob that searched files must match, or must not match if it
+    /// starts with `!`.
+    pub fn glob(mut self, glob: impl Into<String>) -> ConfigBuilder {
+        self.config.globs.push(glob.into());
+        self
+    }
+
+    /// Adds a file type, such as `rust`, to search.
+    pub fn file_type(mut self, name: impl Into<String>) -> ConfigBuilder {
+        self.config.types.push(name.into());
+        self
+    }
+
+    /// Adds a file type not to search.
+    pub fn file_type_not(mut self, name: impl Into<String>) -> ConfigBuilder {
+        self.config.types_not.push(name.into());
+        self
+    }
+
+    pub fn hidden(mut self, yes: bool) -> ConfigBuilder {
+        self.config.hidden = yes;
+        self
+    }
+
+    pub fn no_ignore(mut self, yes: bool) -> ConfigBuilder {
+        self.config.no_ignore = yes;
+        self
+    }
+
+    pub fn search_zip(mut self, yes: bool) -> ConfigBuilder {
+        self.config.search_zip = yes;
+        self
+    }
+
+    /// Reads every file as `encoding`, rather than going by its byte order
+    /// mark.
+    pub fn encoding(mut self, encoding: Encoding) -> ConfigBuilder {
+        self.config.encoding = Some(encoding);
+        self
+    }
+
+    pub fn mmap(mut self, yes: bool) -> ConfigBuilder {
+        self.config.mmap = yes;
+        self
+    }
+
+    pub fn follow(mut self, yes: bool) -> ConfigBuilder {
+        self.config.follow = yes;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> ConfigBuilder {
+        self.config.threads = threads;
+        self
+    }
+
+    /// Finishes the `Config`, failing if its settings conflict, in the same
+    /// way the matching command-line flags would.
+    pub fn build(self) -> Result<Config, ConfigError> {
+        self.config.check()?;
+        Ok(self.config)
+    }
+}
+
+/// A flag's name from `FLAGS` or `OPTIONS`, and its value if it takes one.
+type Flag<'a> = (&'static str, Option<&'a str>);
+
+/// Splits `args` into flags and positional arguments.
+fn parse(args: &[String]) -> Result<(Vec<Flag<'_>>, Vec<&String>), ConfigError> {
+    let mut flags = Vec::new();
+    let mut positional = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            positional.extend(args.by_ref());
+        } else if let Some(long) = arg.strip_prefix("--") {
+            let (long, attached) = match long.split_once('=') {
+                Some((long, value)) => (long, Some(value)),
+                None => (long, None),
+            };
+
+            if let Some(name) = by_long(FLAGS, long) {
+                if attached.is_some() {
+                    return Err(ConfigError::UnexpectedValue(format!("--{long}")));
+                }
+                flags.push((name, None));
+            } else if let Some(name) = by_long(OPTIONS, long) {
+                let value = match attached {
+                    Some(value) => value,
+                    None => args
+                        .next()
+                        .ok_or_else(|| ConfigError::MissingValue(format!("--{long}")))?,
+                };
+                flags.push((name, Some(value)));
+            } else {
+                return Err(ConfigError::UnknownFlag(arg.clone()));
+            }
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            for (index, short) in arg.char_indices().skip(1) {
+                if let Some(name) = by_short(FLAGS, short) {
+                    flags.push((name, None));
+                } else if let Some(name) = by_short(OPTIONS, short) {
+                    let attached = &arg[index + short.len_utf8()..];
+                    let value = if attached.is_empty() {
+                        args.next()
+                            .ok_or_else(|| ConfigError::MissingValue(format!("-{short}")))?
+                    } else {
+                        attached
+                    };
+                    flags.push((name, Some(value)));
+                    break;
+                } else {
+                    return Err(ConfigError::UnknownFlag(format!("-{short}")));
+                }
+            }
+        } else {
+            positional.push(arg);
+        }
+    }
+
+    Ok((flags, positional))
+}
+
+/// Finds and reads the config file, returning its path and contents, or
+/// `None` if there isn't one.
+///
+/// A file named by `MINIGREP_CONFIG` has to exist, but the default one
+/// doesn't.
+fn read_config_file() -> Result<Option<(PathBuf, String)>, ConfigError> {
+    let (path, required) = match env::var_os("MINIGREP_CONFIG") {
+        Some(path) if path.is_empty() => return Ok(None),
+        Some(path) => (PathBuf::from(path), true),
+        None => match default_config_path() {
+            Some(path) => (path, false),
+            None => return Ok(None),
+        },
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => Ok(Some((path, contents))),
+        Err(err) if !required && err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(ConfigError::ConfigFile {
+            path: path.display().to_string(),
+            message: err.to_string(),
+        }),
+    }
+}
+
+/// `$XDG_CONFIG_HOME/minigrep/config`, where `XDG_CONFIG_HOME` defaults to
+/// `~/.config`.
+fn default_config_path() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("minigrep").join("config"))
+}
+
+fn number(name: &str, value: Option<&str>) -> Result<usize, ConfigError> {
+    value
+        .unwrap_or_default()
+        .parse()
+        .map_err(|_| invalid_value(name, value))
+}
+
+fn invalid_value(name: &str, value: Option<&str>) -> ConfigError {
+    ConfigError::InvalidValue {
+        flag: format!("--{name}"),
+        value: value.unwrap_or_default().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn regex_flag_is_not_positional() {
+        let args = args(&["minigrep", "-E", "fr?og", "poem.txt"]);
+        let config = Config::build_without_file(&args).unwrap();
+
+        assert!(config.regex);
+        assert_eq!(vec!["fr?og"], config.patterns);
+        assert_eq!(paths(&["poem.txt"]), config.file_paths);
+    }
+
+    #[test]
+    fn multiple_file_paths() {
+        let args = args(&["minigrep", "to", "poem.txt", "src"]);
+        let config = Config::build_without_file(&args).unwrap();
+
+        assert_eq!(vec!["to"], config.patterns);
+        assert_eq!(paths(&["poem.txt", "src"]), config.file_paths);
+        assert!(Config::build_without_file(&args[..2])
+            .unwrap()
+            .file_paths
+            .is_empty());
+        assert_eq!(
+            Some(ConfigError::MissingQuery),
+            Config::build_without_file(&args[..1]).err()
+        );
+    }
+
+    #[test]
+    fn patterns_from_flags() {
+        let args = args(&[
+            "minigrep",
+            "-e",
+            "to",
+            "-f",
+            "signatures",
+            "--regexp=be",
+            "poem.txt",
+        ]);
+        let config = Config::build_without_file(&args).unwrap();
+
+        assert_eq!(vec!["to", "be"], config.patterns);
+        assert_eq!(paths(&["signatures"]), config.pattern_files);
+        assert_eq!(paths(&["poem.txt"]), config.file_paths);
+    }
+
+    #[test]
+    fn combined_short_flags() {
+        let args = args(&["minigrep", "-nvE", "--count", "to", "poem.txt"]);
+        let config = Config::build_without_file(&args).unwrap();
+
+        assert!(config.line_number && config.invert_match && config.regex && config.count);
+        assert!(!config.files_with_matches);
+    }
+
+    #[test]
+    fn double_dash_ends_flags() {
+        let args = args(&["minigrep", "-i", "--", "-n", "-l"]);
+        let config = Config::build_without_file(&args).unwrap();
+
+        assert!(config.ignore_case && !config.line_number);
+        assert_eq!(vec!["-n"], config.patterns);
+        assert_eq!(paths(&["-l"]), config.file_paths);
+    }
+
+    #[test]
+    fn unknown_flags_are_named() {
+        let err = Config::build_without_file(&args(&["minigrep", "-nQ", "to", "poem.txt"])).err();
+        assert_eq!(Some(ConfigError::UnknownFlag(String::from("-Q"))), err);
+
+        let err =
+            Config::build_without_file(&args(&["minigrep", "--frog", "to", "poem.txt"])).err();
+        assert_eq!(Some(ConfigError::UnknownFlag(String::from("--frog"))), err);
+    }
+
+    #[test]
+    fn help_and_version() {
+        assert_eq!(
+            Some(ConfigError::Help),
+            Config::build_without_file(&args(&["minigrep", "-h"])).err()
+        );
+        assert_eq!(
+            Some(ConfigError::Version),
+            Config::build_without_file(&args(&["minigrep", "--version"])).err()
+        );
+    }
+
+    #[test]
+    fn flag_values() {
+        let args = args(&[
+            "minigrep",
+            "-nC2",
+            "--after-context=4",
+            "-B",
+            "1",
+            "to",
+            "poem.txt",
+        ]);
+        let config = Config::build_without_file(&args).unwrap();
+
+        assert!(config.line_number);
+        assert_eq!((1, 4), (config.before_context, config.after_context));
+
+        let err = Config::build_without_file(&args[..4]).err();
+        assert_eq!(Some(ConfigError::MissingValue(String::from("-B"))), err);
+    }
+
+    #[test]
+    fn invalid_flag_values() {
+        let args = args(&["minigrep", "--context", "lots", "to", "poem.txt"]);
+        let err = Config::build_without_file(&args).err();
+        assert_eq!(
+            Some(ConfigError::InvalidValue {
+                flag: String::from("--context"),
+                value: String::from("lots")
+            }),
+            err
+        );
+    }
+
+    #[test]
+    fn color_choice() {
+        let args = args(&["minigrep", "--color=always", "to"]);
+        let config = Config::build_without_file(&args).unwrap();
+        assert_eq!(ColorChoice::Always, config.color);
+    }
+
+    #[test]
+    fn invalid_color_choice() {
+        let args = args(&["minigrep", "--color", "sometimes", "to"]);
+        let err = Config::build_without_file(&args).err();
+        assert_eq!(
+            Some(ConfigError::InvalidValue {
+                flag: String::from("--color"),
+                value: String::from("sometimes")
+            }),
+            err
+        );
+    }
+
+    #[test]
+    fn json_conflicts_with_summaries() {
+        let args = args(&["minigrep", "--json", "-c", "to"]);
+        let err = Config::build_without_file(&args).err();
+        assert_eq!(
+            Some(ConfigError::Conflict(
+                String::from("--json"),
+                String::from("--count")
+            )),
+            err
+        );
+    }
+
+    #[test]
+    fn builder_matches_command_line() {
+        let args = args(&["minigrep", "-in", "--glob=*.txt", "-e", "to", "poem.txt"]);
+        let parsed = Config::build_without_file(&args).unwrap();
+
+        let built = Config::builder()
+            .pattern("to")
+            .ignore_case(true)
+            .line_number(true)
+            .glob("*.txt")
+            .file_path("poem.txt")
+            .build()
+            .unwrap();
+
+        assert_eq!(parsed, built);
+    }
+
+    #[test]
+    fn builder_checks_conflicts() {
+        let err = Config::builder()
+            .pattern("to")
+            .json(true)
+            .count(true)
+            .build();
+        assert_eq!(
+            Some(ConfigError::Conflict(
+                String::from("--json"),
+                String::from("--count")
+            )),
+            err.err()
+        );
+        assert!(Config::builder().threads(0).build().is_err());
+        assert!(Config::builder()
+            .follow(true)
+            .multiline(true)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn fields_can_carry_the_query() {
+        let config = Config::build_without_file(&args(&[
+            "minigrep",
+            "--field",
+            "level=ERROR",
+            "--select",
+            "time,message",
+            "log.csv",
+        ]))
+        .unwrap();
+        assert_eq!(vec![String::from("ERROR")], config.patterns);
+        assert_eq!(Some(String::from("level")), config.field);
+        assert_eq!(
+            vec![String::from("time"), String::from("message")],
+            config.select
+        );
+        assert_eq!(paths(&["log.csv"]), config.file_paths);
+
+        let config =
+            Config::build_without_file(&args(&["minigrep", "--jsonpath", ".user.id", "42"]))
+                .unwrap();
+        assert_eq!(vec![String::from("42")], config.patterns);
+        assert!(config.file_paths.is_empty());
+    }
+
+    #[test]
+    fn in_place_needs_replace() {
+        let err =
+            Config::build_without_file(&args(&["minigrep", "--in-place", "to", "poem.txt"])).err();
+        assert_eq!(
+            Some(ConfigError::Requires(
+                String::from("--in-place"),
+                String::from("--replace")
+            )),
+            err
+        );
+
+        let args = args(&[
+            "minigrep",
+            "--replace=$1",
+            "--in-place",
+            "-E",
+            "(to)",
+            "poem.txt",
+        ]);
+        let config = Config::build_without_file(&args).unwrap();
+        assert_eq!(Some(String::from("$1")), config.replace);
+        assert!(config.in_place);
+
+        let err = Config::builder()
+            .replace("")
+            .in_place(true)
+            .json(true)
+            .build();
+        assert_eq!(
+            Some(ConfigError::Conflict(
+                String::from("--json"),
+                String::from("--in-place")
+            )),
+            err.err()
+        );
+    }
+
+    #[test]
+    fn config_file_defaults() {
+        let contents = "\
+# Shared team settings.
+--color=always
+--smart-case
+
+-g
+!*.min.js
+";
+        let args = args(&["minigrep", "-s", "--color", "never", "to"]);
+        let config = Config::build_with_defaults(&args, Path::new("config"), contents).unwrap();
+
+        assert_eq!(ColorChoice::Never, config.color);
+        assert!(!config.smart_case && !config.ignore_case);
+        assert_eq!(vec!["!*.min.js"], config.globs);
+    }
+
+    #[test]
+    fn command_line_turns_off_config_file_switches() {
+        let contents = "--json\n-n\n--hidden\n--no-ignore\n";
+
+        let build = |command_line: &[&str]| {
+            Config::build_with_defaults(&args(command_line), Path::new("config"), contents)
+        };
+
+        let err = build(&["minigrep", "-c", "to"]).err();
+        assert_eq!(
+            Some(ConfigError::Conflict(
+                String::from("--json"),
+                String::from("--count")
+            )),
+            err
+        );
+
+        let config = build(&[
+            "minigrep",
+            "--no-json",
+            "--no-line-number",
+            "--no-hidden",
+            "--ignore",
+            "-c",
+            "to",
+        ])
+        .unwrap();
+        assert!(config.count);
+        assert!(!config.json && !config.line_number && !config.hidden && !config.no_ignore);
+
+        // Switches that change what's printed, or how, can be turned off too.
+        let contents = "-c
+-v
+-l
+-q
+--follow
+--interactive
+";
+        let config = Config::build_with_defaults(
+            &args(&[
+                "minigrep",
+                "--no-count",
+                "--no-invert-match",
+                "--no-files-with-matches",
+                "--no-quiet",
+                "--no-follow",
+                "--no-interactive",
+                "to",
+            ]),
+            Path::new("config"),
+            contents,
+        )
+        .unwrap();
+        assert!(!config.count && !config.invert_match && !config.files_with_matches);
+        assert!(!config.quiet && !config.follow && !config.interactive);
+
+        let contents = "-L
+--replace=x
+--in-place
+";
+        let config = Config::build_with_defaults(
+            &args(&[
+                "minigrep",
+                "--no-files-without-match",
+                "--no-in-place",
+                "to",
+            ]),
+            Path::new("config"),
+            contents,
+        )
+        .unwrap();
+        assert!(!config.files_without_match && !config.in_place);
+    }
+
+    #[test]
+    fn config_file_mistakes_name_the_file() {
+        let args = args(&["minigrep", "to"]);
+        let in_file = |message: &str| {
+            Some(ConfigError::ConfigFile {
+                path: String::from("config"),
+                message: String::from(message),
+            })
+        };
+
+        let err = Config::build_with_defaults(&args, Path::new("config"), "--frog").err();
+        assert_eq!(in_file("unknown flag '--frog'"), err);
+
+        let err = Config::build_with_defaults(&args, Path::new("config"), "frog").err();
+        assert_eq!(in_file("'frog' is not a flag"), err);
+
+        let err = Config::build_with_defaults(&args, Path::new("config"), "-A\nlots").err();
+        assert_eq!(
+            in_file("invalid value 'lots' for flag '--after-context'"),
+            err
+        );
+    }
+}
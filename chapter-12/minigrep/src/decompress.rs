@@ -0,0 +1,146 @@
+//! Reading compressed files for `-z`, by piping them through the usual
+//! command-line tool for their format, as ripgrep does.
+
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::thread::{self, JoinHandle};
+
+/// The program and arguments that decompress a file to stdout, by the
+/// file's extension.
+const DECOMPRESSORS: &[(&str, &str, &[&str])] = &[
+    ("gz", "gzip", &["-d", "-c"]),
+    ("tgz", "gzip", &["-d", "-c"]),
+    ("zst", "zstd", &["-d", "-c", "-q"]),
+    ("zstd", "zstd", &["-d", "-c", "-q"]),
+    ("bz2", "bzip2", &["-d", "-c"]),
+    ("tbz2", "bzip2", &["-d", "-c"]),
+    ("xz", "xz", &["-d", "-c"]),
+    ("txz", "xz", &["-d", "-c"]),
+    ("lzma", "xz", &["--format=lzma", "-d", "-c"]),
+];
+
+/// The decompressed contents of a file, read from the output of the program
+/// that decompresses it.
+pub struct Decompressed {
+    program: &'static str,
+    child: Child,
+    stdout: ChildStdout,
+    /// Collects what the program writes to stderr. This has to happen while
+    /// stdout is being read, since a program that fills the stderr pipe
+    /// would otherwise wait forever for it to be emptied.
+    stderr: Option<JoinHandle<io::Result<String>>>,
+}
+
+/// Starts decompressing the file at `path`, or returns `None` if its
+/// extension isn't one of a known compressed format.
+pub fn open(path: &Path) -> io::Result<Option<Decompressed>> {
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let Some((_, program, args)) = DECOMPRESSORS
+        .iter()
+        .find(|(known, _, _)| Some(*known) == extension)
+    else {
+        return Ok(None);
+    };
+
+    let mut child = Command::new(program)
+        .args(*args)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| io::Error::new(err.kind(), format!("couldn't run {program}: {err}")))?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stderr = thread::spawn(move || {
+        let mut message = String::new();
+        stderr.read_to_string(&mut message).map(|_| message)
+    });
+
+    Ok(Some(Decompressed {
+        program,
+        child,
+        stdout,
+        stderr: Some(stderr),
+    }))
+}
+
+impl Read for Decompressed {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.stdout.read(buf)?;
+
+        // At the end of the output, make sure it wasn't cut short because
+        // the file is corrupt.
+        if read == 0 && !buf.is_empty() {
+            let stderr = match self.stderr.take() {
+                Some(reading) => reading
+                    .join()
+                    .map_err(|_| io::Error::other("reading stderr panicked"))??,
+                None => String::new(),
+            };
+
+            let status = self.child.wait()?;
+            if !status.success() {
+                let program = self.program;
+                let message = stderr.trim();
+                return Err(io::Error::other(format!("{program} failed: {message}")));
+            }
+        }
+
+        Ok(read)
+    }
+}
+
+impl Drop for Decompressed {
+    /// Stops the program if the search finished before reading everything,
+    /// as `-l` and `-q` do.
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        // The program is gone, so its stderr has been closed.
+        if let Some(reading) = self.stderr.take() {
+            let _ = reading.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// "How public, like a frog\n", compressed with gzip.
+    const FROG_GZ: &[u8] = &[
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xf3, 0xc8, 0x2f, 0x57, 0x28,
+        0x28, 0x4d, 0xca, 0xc9, 0x4c, 0xd6, 0x51, 0xc8, 0xc9, 0xcc, 0x4e, 0x55, 0x48, 0x54, 0x48,
+        0x2b, 0xca, 0x4f, 0xe7, 0x02, 0x00, 0xdd, 0x27, 0x75, 0xe5, 0x18, 0x00, 0x00, 0x00,
+    ];
+
+    fn decompress(name: &str, contents: &[u8]) -> Option<io::Result<String>> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(name);
+        fs::write(&path, contents).unwrap();
+
+        open(&path).unwrap().map(|mut reader| {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).map(|_| text)
+        })
+    }
+
+    #[test]
+    fn decompresses_by_extension() {
+        let text = decompress("frog.txt.gz", FROG_GZ).unwrap().unwrap();
+        assert_eq!("How public, like a frog\n", text);
+
+        assert!(decompress("frog.txt", FROG_GZ).is_none());
+    }
+
+    #[test]
+    fn corrupt_files_are_errors() {
+        let err = decompress("broken.gz", &FROG_GZ[..20])
+            .unwrap()
+            .unwrap_err();
+        assert!(err.to_string().starts_with("gzip failed: "), "{err}");
+    }
+}
@@ -0,0 +1,169 @@
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{lines, walk, Searcher};
+
+/// Added to a file's name to make the name of the backup kept by
+/// `--in-place`. If that backup already exists, a number is added after it,
+/// as in `poem.txt.bak.1`.
+const BACKUP_SUFFIX: &str = ".bak";
+
+/// Added to a file's name to make the name of the file its new contents are
+/// written to before they replace it.
+const TEMP_SUFFIX: &str = ".minigrep-tmp";
+
+/// Rewrites the file at `path` with the searcher's replacement substituted
+/// for every match, as `--in-place` does, and returns how many lines changed.
+///
+/// Files with nothing to change are left alone. Otherwise the original is
+/// first copied to a backup next to it, never overwriting an earlier
+/// backup. The new contents are written to a
+/// temporary file in the same directory, which then takes the original's
+/// place in a single rename, so the file is never seen half-written.
+pub fn in_place(searcher: &Searcher, path: &Path) -> Result<usize, Box<dyn Error>> {
+    let contents = fs::read(path)?;
+    if walk::is_binary(&contents) {
+        return Ok(0);
+    }
+    // Rewriting a lossily decoded line would corrupt the bytes that weren't
+    // valid UTF-8.
+    let contents =
+        String::from_utf8(contents).map_err(|_| "not valid UTF-8, so it can't be edited")?;
+
+    let mut edited = String::with_capacity(contents.len());
+    let mut written = 0;
+    let mut changed = 0;
+
+    for mut line in lines(&contents) {
+        if !searcher.select(&mut line) || line.submatches.is_empty() {
+            continue;
+        }
+
+        let byte_range = line.byte_range.clone();
+        edited.push_str(&contents[written..byte_range.start]);
+        edited.push_str(&searcher.replace(line).text);
+        written = byte_range.end;
+        changed += 1;
+    }
+
+    if changed == 0 {
+        return Ok(0);
+    }
+    edited.push_str(&contents[written..]);
+
+    back_up(path, &contents)?;
+
+    let temp = with_suffix(path, TEMP_SUFFIX);
+    let replaced = write_file(&temp, &edited, path).and_then(|()| Ok(fs::rename(&temp, path)?));
+    if replaced.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    replaced?;
+
+    Ok(changed)
+}
+
+/// Returns `true` if `path` looks like one of the backups or temporary files
+/// that `--in-place` leaves next to the files it edits, so that searching the
+/// same directory again doesn't edit them too.
+pub fn is_leftover(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    if name.ends_with(BACKUP_SUFFIX) || name.ends_with(TEMP_SUFFIX) {
+        return true;
+    }
+    name.rsplit_once('.').is_some_and(|(rest, number)| {
+        rest.ends_with(BACKUP_SUFFIX)
+            && !number.is_empty()
+            && number.bytes().all(|byte| byte.is_ascii_digit())
+    })
+}
+
+/// Copies `contents`, the contents of the file at `path`, to the first of
+/// `FILE.bak`, `FILE.bak.1`, `FILE.bak.2` and so on that doesn't exist yet.
+fn back_up(path: &Path, contents: &str) -> Result<(), Box<dyn Error>> {
+    for number in 0.. {
+        let backup = match number {
+            0 => with_suffix(path, BACKUP_SUFFIX),
+            number => with_suffix(path, &format!("{BACKUP_SUFFIX}.{number}")),
+        };
+        let mut file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&backup)
+        {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.into()),
+        };
+        file.write_all(contents.as_bytes())?;
+        file.set_permissions(fs::metadata(path)?.permissions())?;
+        return Ok(());
+    }
+    unreachable!("there is always another number to try")
+}
+
+/// Writes `contents` to a new file at `path` with the same permissions as
+/// `original`, making sure it has reached the disk.
+fn write_file(path: &Path, contents: &str, original: &Path) -> Result<(), Box<dyn Error>> {
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())?;
+    file.set_permissions(fs::metadata(original)?.permissions())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn rewrites_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("poem.txt");
+        let original =
+            "I'm nobody! Who are you?\r\nAre you nobody, too?\nThen there's a pair of us";
+        fs::write(&path, original).unwrap();
+
+        let config = Config::builder()
+            .pattern(r"(\w+)body")
+            .regex(true)
+            .replace("${1}one")
+            .build()
+            .unwrap();
+        let changed = in_place(&Searcher::new(&config).unwrap(), &path).unwrap();
+
+        let edited = fs::read_to_string(&path).unwrap();
+        let backup = fs::read_to_string(dir.path().join("poem.txt.bak")).unwrap();
+        let leftovers = fs::read_dir(dir.path()).unwrap().count();
+
+        assert_eq!(2, changed);
+        assert_eq!(
+            "I'm noone! Who are you?\r\nAre you noone, too?\nThen there's a pair of us",
+            edited
+        );
+        assert_eq!(original, backup);
+        assert_eq!(2, leftovers);
+    }
+
+    #[test]
+    fn recognises_leftovers() {
+        assert!(is_leftover(Path::new("dir/poem.txt.bak")));
+        assert!(is_leftover(Path::new("dir/poem.txt.bak.12")));
+        assert!(is_leftover(Path::new("poem.txt.minigrep-tmp")));
+        assert!(!is_leftover(Path::new("poem.txt")));
+        assert!(!is_leftover(Path::new("poem.bak.txt")));
+        assert!(!is_leftover(Path::new("poem.txt.bak.")));
+    }
+}
@@ -0,0 +1,321 @@
+//! Reading text that isn't UTF-8.
+//!
+//! Files are searched as UTF-8 unless they start with a byte order mark (BOM)
+//! saying otherwise, or `--encoding` names another encoding. Anything else is
+//! transcoded to UTF-8 as it is read, so the rest of minigrep only ever sees
+//! UTF-8. Bytes that aren't valid in the encoding become U+FFFD.
+
+use std::io::{self, BufRead, BufReader, Read};
+
+/// The encodings that files can be transcoded from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1, where every byte is the code point of the same value.
+    Latin1,
+    /// Latin-1 with printable characters such as `€` and curly quotes in
+    /// place of most of the C1 control codes.
+    Windows1252,
+}
+
+/// Names accepted by `--encoding`, in lowercase.
+const LABELS: &[(&str, Encoding)] = &[
+    ("utf-8", Encoding::Utf8),
+    ("utf8", Encoding::Utf8),
+    ("utf-16le", Encoding::Utf16Le),
+    ("utf-16be", Encoding::Utf16Be),
+    ("latin1", Encoding::Latin1),
+    ("latin-1", Encoding::Latin1),
+    ("iso-8859-1", Encoding::Latin1),
+    ("windows-1252", Encoding::Windows1252),
+    ("cp1252", Encoding::Windows1252),
+];
+
+/// The characters for bytes 0x80 to 0x9F in Windows-1252. The five bytes it
+/// leaves undefined map to the control codes of the same value, as they do
+/// in web browsers.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+impl Encoding {
+    /// Looks up an encoding by name, ignoring case.
+    pub fn from_label(label: &str) -> Option<Encoding> {
+        let label = label.to_ascii_lowercase();
+        LABELS
+            .iter()
+            .find(|(name, _)| *name == label)
+            .map(|&(_, encoding)| encoding)
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => b"\xEF\xBB\xBF",
+            Encoding::Utf16Le => b"\xFF\xFE",
+            Encoding::Utf16Be => b"\xFE\xFF",
+            Encoding::Latin1 | Encoding::Windows1252 => b"",
+        }
+    }
+
+    /// The encoding whose BOM `contents` starts with, if any.
+    fn sniff(contents: &[u8]) -> Option<Encoding> {
+        [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be]
+            .into_iter()
+            .find(|encoding| contents.starts_with(encoding.bom()))
+    }
+}
+
+/// Text read from a `BufRead`, transcoded to UTF-8 if necessary.
+pub enum Decoded<R> {
+    /// The text is already UTF-8, and is passed through untouched.
+    Utf8(R),
+    Transcoded(BufReader<Transcoder<R>>),
+}
+
+/// Prepares to read `reader` as UTF-8, skipping any BOM.
+///
+/// With no `encoding`, the BOM decides, and text without one is taken to be
+/// UTF-8. Otherwise text is read in `encoding` whatever it starts with, but a
+/// BOM for that encoding is still skipped.
+pub fn decode<R: BufRead>(mut reader: R, encoding: Option<Encoding>) -> io::Result<Decoded<R>> {
+    let start = reader.fill_buf()?;
+    let encoding = match encoding {
+        Some(encoding) => encoding,
+        None => Encoding::sniff(start).unwrap_or(Encoding::Utf8),
+    };
+    if start.starts_with(encoding.bom()) {
+        let len = encoding.bom().len();
+        reader.consume(len);
+    }
+
+    Ok(match encoding {
+        Encoding::Utf8 => Decoded::Utf8(reader),
+        encoding => Decoded::Transcoded(BufReader::new(Transcoder::new(reader, encoding))),
+    })
+}
+
+/// Returns `contents` without any BOM if it would be read as UTF-8, the way
+/// `decode` decides, or `None` if it would have to be transcoded.
+pub fn as_utf8(contents: &[u8], encoding: Option<Encoding>) -> Option<&[u8]> {
+    let encoding = encoding.unwrap_or_else(|| Encoding::sniff(contents).unwrap_or(Encoding::Utf8));
+    (encoding == Encoding::Utf8).then(|| {
+        contents
+            .strip_prefix(Encoding::Utf8.bom())
+            .unwrap_or(contents)
+    })
+}
+
+impl<R: BufRead> Read for Decoded<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Decoded::Utf8(reader) => reader.read(buf),
+            Decoded::Transcoded(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for Decoded<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Decoded::Utf8(reader) => reader.fill_buf(),
+            Decoded::Transcoded(reader) => reader.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amount: usize) {
+        match self {
+            Decoded::Utf8(reader) => reader.consume(amount),
+            Decoded::Transcoded(reader) => reader.consume(amount),
+        }
+    }
+}
+
+/// Reads text in some other encoding as UTF-8.
+pub struct Transcoder<R> {
+    reader: R,
+    encoding: Encoding,
+    /// Transcoded text that hasn't been read yet, starting at `next`.
+    output: String,
+    next: usize,
+    /// The end of a UTF-16 character that was split between reads.
+    unfinished: Vec<u8>,
+}
+
+impl<R: BufRead> Transcoder<R> {
+    fn new(reader: R, encoding: Encoding) -> Transcoder<R> {
+        Transcoder {
+            reader,
+            encoding,
+            output: String::new(),
+            next: 0,
+            unfinished: Vec::new(),
+        }
+    }
+
+    /// Transcodes the next block of input into `output`, leaving it empty
+    /// only at the end of the input.
+    fn refill(&mut self) -> io::Result<()> {
+        self.output.clear();
+        self.next = 0;
+
+        while self.output.is_empty() {
+            let input = self.reader.fill_buf()?;
+            let at_end = input.is_empty();
+            let len = input.len();
+
+            match self.encoding {
+                Encoding::Utf8 => unreachable!("UTF-8 is never transcoded"),
+                Encoding::Latin1 => self
+                    .output
+                    .extend(input.iter().map(|&byte| char::from(byte))),
+                Encoding::Windows1252 => self.output.extend(input.iter().map(|&byte| match byte {
+                    0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(byte - 0x80)],
+                    _ => char::from(byte),
+                })),
+                Encoding::Utf16Le | Encoding::Utf16Be => {
+                    self.unfinished.extend_from_slice(input);
+                    let big_endian = self.encoding == Encoding::Utf16Be;
+                    let used = decode_utf16(&self.unfinished, big_endian, at_end, &mut self.output);
+                    self.unfinished.drain(..used);
+                }
+            }
+
+            self.reader.consume(len);
+            if at_end {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for Transcoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.next == self.output.len() {
+            self.refill()?;
+        }
+
+        let remaining = &self.output.as_bytes()[self.next..];
+        let len = remaining.len().min(buf.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        self.next += len;
+        Ok(len)
+    }
+}
+
+/// Decodes as much of `input` as possible, appending it to `output`, and
+/// returns how many bytes were used. A character split across the end of
+/// `input` is left for next time, unless this is the end of the text.
+fn decode_utf16(input: &[u8], big_endian: bool, at_end: bool, output: &mut String) -> usize {
+    let mut units: Vec<u16> = input
+        .chunks_exact(2)
+        .map(|pair| {
+            let pair = [pair[0], pair[1]];
+            if big_endian {
+                u16::from_be_bytes(pair)
+            } else {
+                u16::from_le_bytes(pair)
+            }
+        })
+        .collect();
+
+    let leading_surrogate = |unit: &u16| (0xD800..0xDC00).contains(unit);
+    if !at_end && units.last().is_some_and(leading_surrogate) {
+        units.pop();
+    }
+
+    output.extend(
+        char::decode_utf16(units.iter().copied())
+            .map(|decoded| decoded.unwrap_or(char::REPLACEMENT_CHARACTER)),
+    );
+
+    let used = units.len() * 2;
+    if at_end && used < input.len() {
+        // Half a code unit.
+        output.push(char::REPLACEMENT_CHARACTER);
+        return input.len();
+    }
+    used
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(input: &[u8], encoding: Option<Encoding>) -> String {
+        let mut text = String::new();
+        decode(input, encoding)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| {
+                if big_endian {
+                    unit.to_be_bytes()
+                } else {
+                    unit.to_le_bytes()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn byte_order_marks_decide_the_encoding() {
+        let text = "Rust:\r\nsafe, fast, productive. 🦀\n";
+
+        let mut little = b"\xFF\xFE".to_vec();
+        little.extend(utf16(text, false));
+        let mut big = b"\xFE\xFF".to_vec();
+        big.extend(utf16(text, true));
+        let mut utf8 = b"\xEF\xBB\xBF".to_vec();
+        utf8.extend(text.as_bytes());
+
+        assert_eq!(text, read(&little, None));
+        assert_eq!(text, read(&big, None));
+        assert_eq!(text, read(&utf8, None));
+        assert_eq!(text, read(text.as_bytes(), None));
+
+        assert_eq!(Some(text.as_bytes()), as_utf8(&utf8, None));
+        assert_eq!(Some(text.as_bytes()), as_utf8(text.as_bytes(), None));
+        assert_eq!(None, as_utf8(&little, None));
+        assert_eq!(None, as_utf8(&utf8, Some(Encoding::Latin1)));
+    }
+
+    #[test]
+    fn characters_split_between_reads() {
+        let input = utf16("a🦀b", false);
+        // A one-byte buffer splits every code unit, and the crab's surrogate
+        // pair, across reads.
+        let reader = BufReader::with_capacity(1, &input[..]);
+        let mut text = String::new();
+        decode(reader, Some(Encoding::Utf16Le))
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!("a🦀b", text);
+
+        // An odd byte at the end, or a lone surrogate, is replaced.
+        assert_eq!("a\u{FFFD}", read(b"a\x00b", Some(Encoding::Utf16Le)));
+        assert_eq!("\u{FFFD}a", read(b"\x3D\xD8a\x00", Some(Encoding::Utf16Le)));
+    }
+
+    #[test]
+    fn single_byte_encodings() {
+        let input = b"caf\xE9 \x93quoted\x94 \x80";
+        assert_eq!(
+            "café \u{93}quoted\u{94} \u{80}",
+            read(input, Some(Encoding::Latin1))
+        );
+        assert_eq!("café “quoted” €", read(input, Some(Encoding::Windows1252)));
+        assert_eq!(Some(Encoding::Windows1252), Encoding::from_label("CP1252"));
+        assert_eq!(None, Encoding::from_label("ebcdic"));
+    }
+}
@@ -0,0 +1,375 @@
+//! Searching one field of structured records, for `--field` and `--jsonpath`.
+//!
+//! Each line is taken to be a record: a row of a CSV file whose first line
+//! names the columns, or a JSON value in a JSON Lines file. The query is
+//! matched against a single field rather than the whole line, and `--select`
+//! can print just some of the fields of each line.
+//!
+//! CSV is read as RFC 4180 describes, except that a record can't span lines.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use serde_json::Value;
+
+use crate::Config;
+
+/// How each line lays out its fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Comma-separated values, with a header naming the columns.
+    Csv,
+    /// A JSON value, whose fields are named by paths such as `.user.id`.
+    JsonLines,
+}
+
+/// The field that is searched and the fields that are printed.
+pub struct Fields {
+    format: Format,
+    field: String,
+    select: Vec<String>,
+}
+
+/// Where the named columns are in a CSV file, found from its header. A
+/// column that isn't in the header is `None`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Columns {
+    field: Option<usize>,
+    select: Vec<Option<usize>>,
+}
+
+/// The value of the searched field in a line.
+pub struct Field<'a> {
+    pub value: Cow<'a, str>,
+    /// Where `value` starts in the line, if it appears there exactly as it
+    /// is, so that matches within it can be highlighted.
+    pub start: Option<usize>,
+}
+
+impl Fields {
+    /// Returns the fields named by `config`, if it has `--field` or
+    /// `--jsonpath`.
+    pub fn new(config: &Config) -> Option<Fields> {
+        let (format, field) = match (&config.field, &config.jsonpath) {
+            (Some(column), _) => (Format::Csv, column),
+            (None, Some(path)) => (Format::JsonLines, path),
+            (None, None) => return None,
+        };
+
+        Some(Fields {
+            format,
+            field: field.clone(),
+            select: config.select.clone(),
+        })
+    }
+
+    /// Whether the first line of a file names its fields rather than being
+    /// a record.
+    pub fn has_header(&self) -> bool {
+        self.format == Format::Csv
+    }
+
+    /// Finds the named columns in a CSV header.
+    pub fn columns(&self, header: &str) -> Columns {
+        let names: Vec<Cow<str>> = split_csv(header)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let find = |name: &String| names.iter().position(|column| column == name);
+
+        Columns {
+            field: find(&self.field),
+            select: self.select.iter().map(find).collect(),
+        }
+    }
+
+    /// Returns the first column named in `columns` that its header lacks.
+    pub fn missing<'f>(&'f self, columns: &Columns) -> Option<&'f str> {
+        let field = columns.field.is_none().then_some(&self.field);
+        let select = self
+            .select
+            .iter()
+            .zip(&columns.select)
+            .find_map(|(name, column)| column.is_none().then_some(name));
+
+        field.or(select).map(String::as_str)
+    }
+
+    /// Returns the searched field of `line`, or `None` if it doesn't have
+    /// one. `columns` is only used for CSV.
+    pub fn field<'a>(&self, line: &'a str, columns: &Columns) -> Option<Field<'a>> {
+        match self.format {
+            Format::Csv => {
+                let (value, _) = split_csv(line).into_iter().nth(columns.field?)?;
+                // A value only appears in the line as it is if it didn't have
+                // to be unescaped, in which case it borrows from the line.
+                let start = match &value {
+                    Cow::Borrowed(part) => offset_in(line, part),
+                    Cow::Owned(_) => None,
+                };
+                Some(Field { value, start })
+            }
+            Format::JsonLines => {
+                let record: Value = serde_json::from_str(line).ok()?;
+                Some(Field {
+                    value: Cow::Owned(json_text(lookup(&record, &self.field)?)),
+                    start: None,
+                })
+            }
+        }
+    }
+
+    /// Returns where in `line` the CSV value around byte `at` lies, if
+    /// the value appears there as it is. A match found in the searched field
+    /// can only be highlighted if it does, so this finds the field from the
+    /// match without needing the header.
+    pub fn span_at(&self, line: &str, at: usize) -> Option<Range<usize>> {
+        if self.format != Format::Csv {
+            return None;
+        }
+        let (value, _) = split_csv(line)
+            .into_iter()
+            .find(|(_, span)| span.contains(&at) || span.end == at)?;
+        let start = match &value {
+            Cow::Borrowed(part) => offset_in(line, part)?,
+            Cow::Owned(_) => return None,
+        };
+        Some(start..start + value.len())
+    }
+
+    /// Returns the selected fields of `line` as a CSV record, or `None` if
+    /// no fields are selected. Fields that `line` lacks are left empty.
+    pub fn project(&self, line: &str, columns: &Columns) -> Option<String> {
+        if self.select.is_empty() {
+            return None;
+        }
+
+        let values: Vec<String> = match self.format {
+            Format::Csv => {
+                let values = split_csv(line);
+                columns
+                    .select
+                    .iter()
+                    .map(|column| {
+                        column
+                            .and_then(|column| values.get(column))
+                            .map_or_else(String::new, |(value, _)| value.to_string())
+                    })
+                    .collect()
+            }
+            Format::JsonLines => {
+                let record: Value = serde_json::from_str(line).unwrap_or(Value::Null);
+                self.select
+                    .iter()
+                    .map(|path| lookup(&record, path).map(json_text).unwrap_or_default())
+                    .collect()
+            }
+        };
+
+        let quoted: Vec<Cow<str>> = values.iter().map(|value| quote_csv(value)).collect();
+        Some(quoted.join(","))
+    }
+}
+
+/// Splits a CSV record into its values, each with the span of the line it
+/// came from, quotes included.
+fn split_csv(line: &str) -> Vec<(Cow<'_, str>, Range<usize>)> {
+    let mut values = Vec::new();
+    let mut start = 0;
+
+    loop {
+        let rest = &line[start..];
+        let (value, len) = match rest.strip_prefix('"') {
+            Some(quoted) => quoted_value(quoted),
+            None => {
+                let len = rest.find(',').unwrap_or(rest.len());
+                (Cow::Borrowed(&rest[..len]), len)
+            }
+        };
+
+        values.push((value, start..start + len));
+        start += len;
+        if start >= line.len() {
+            return values;
+        }
+        // Skip the comma.
+        start += 1;
+        if start == line.len() {
+            values.push((Cow::Borrowed(""), start..start));
+            return values;
+        }
+    }
+}
+
+/// Reads a quoted value from just after its opening quote, returning it and
+/// how many bytes it took up, quotes included. Anything between the closing
+/// quote and the next comma is kept as part of the value.
+fn quoted_value(quoted: &str) -> (Cow<'_, str>, usize) {
+    let mut value: Cow<str> = Cow::Borrowed(&quoted[..0]);
+    let mut rest = quoted;
+
+    loop {
+        let Some(quote) = rest.find('"') else {
+            // An unterminated quote runs to the end of the line.
+            push(&mut value, rest);
+            return (value, quoted.len() + 1);
+        };
+        push(&mut value, &rest[..quote]);
+        rest = &rest[quote + 1..];
+
+        match rest.strip_prefix('"') {
+            Some(after) => {
+                value.to_mut().push('"');
+                rest = after;
+            }
+            None => {
+                let trailing = rest.find(',').unwrap_or(rest.len());
+                push(&mut value, &rest[..trailing]);
+                let used = quoted.len() - rest.len() + trailing;
+                return (value, used + 1);
+            }
+        }
+    }
+}
+
+/// Returns where `part`, which must be a slice of `line`, starts in it.
+fn offset_in(line: &str, part: &str) -> Option<usize> {
+    let start = (part.as_ptr() as usize).checked_sub(line.as_ptr() as usize)?;
+    let in_line = start + part.len() <= line.len() && line.is_char_boundary(start);
+    in_line.then_some(start)
+}
+
+/// Appends `more` to `value`, without copying if `value` is still empty.
+fn push<'a>(value: &mut Cow<'a, str>, more: &'a str) {
+    if value.is_empty() {
+        *value = Cow::Borrowed(more);
+    } else if !more.is_empty() {
+        value.to_mut().push_str(more);
+    }
+}
+
+/// Quotes `value` for a CSV record if it needs it.
+fn quote_csv(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Follows a path such as `.user.id` or `.tags.0` into `record`. The path
+/// `.` is the whole record.
+fn lookup<'v>(record: &'v Value, path: &str) -> Option<&'v Value> {
+    let path = path.strip_prefix('.').unwrap_or(path);
+    if path.is_empty() {
+        return Some(record);
+    }
+
+    path.split('.').try_fold(record, |value, step| match value {
+        Value::Object(object) => object.get(step),
+        Value::Array(array) => array.get(step.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// The text a JSON value is searched as: a string's contents, or any other
+/// value's JSON.
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(config: Config) -> Fields {
+        Fields::new(&config).unwrap()
+    }
+
+    #[test]
+    fn splits_csv_records() {
+        let values: Vec<(String, Range<usize>)> = split_csv(r#"a,"b, c",,"say ""hi""","#)
+            .into_iter()
+            .map(|(value, span)| (value.into_owned(), span))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (String::from("a"), 0..1),
+                (String::from("b, c"), 2..8),
+                (String::new(), 9..9),
+                (String::from("say \"hi\""), 10..22),
+                (String::new(), 23..23),
+            ],
+            values
+        );
+    }
+
+    #[test]
+    fn csv_columns_by_name() {
+        let fields = fields(Config {
+            field: Some(String::from("level")),
+            select: vec![String::from("message"), String::from("time")],
+            ..Config::default()
+        });
+        let columns = fields.columns("time,level,message");
+        assert_eq!(None, fields.missing(&columns));
+
+        let line = r#"09:00,"ERROR","disk full, again""#;
+        let field = fields.field(line, &columns).unwrap();
+        assert_eq!("ERROR", field.value);
+        assert_eq!(Some(7), field.start);
+        assert_eq!(
+            Some(String::from(r#""disk full, again",09:00"#)),
+            fields.project(line, &columns)
+        );
+
+        assert_eq!(
+            Some("level"),
+            fields.missing(&fields.columns("time,message"))
+        );
+    }
+
+    #[test]
+    fn csv_field_positions() {
+        let fields = fields(Config {
+            field: Some(String::from("name")),
+            ..Config::default()
+        });
+        let columns = fields.columns("id,name");
+        let field = |line| {
+            let field = fields.field(line, &columns).unwrap();
+            (field.value.into_owned(), field.start)
+        };
+
+        assert_eq!((String::from("ab"), Some(2)), field("1,ab"));
+        assert_eq!((String::from("ab"), Some(3)), field(r#"1,"ab""#));
+        // An empty quoted value followed by more text.
+        assert_eq!((String::from("é"), Some(4)), field(r#"1,""é"#));
+        assert_eq!((String::from("ab"), Some(4)), field(r#"1,""ab"#));
+        assert_eq!((String::from("a\"b"), None), field(r#"1,"a""b""#));
+    }
+
+    #[test]
+    fn json_paths() {
+        let fields = fields(Config {
+            jsonpath: Some(String::from(".user.id")),
+            select: vec![String::from(".tags.1"), String::from(".missing")],
+            ..Config::default()
+        });
+        let columns = Columns::default();
+
+        let line = r#"{"user": {"id": 42}, "tags": ["a", "b,c"]}"#;
+        assert_eq!("42", fields.field(line, &columns).unwrap().value);
+        assert_eq!(
+            Some(String::from(r#""b,c","#)),
+            fields.project(line, &columns)
+        );
+
+        assert!(fields.field(r#"{"user": null}"#, &columns).is_none());
+        assert!(fields.field("not json", &columns).is_none());
+    }
+}
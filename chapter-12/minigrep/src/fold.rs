@@ -0,0 +1,533 @@
+//! Unicode case folding, for comparing text without regard to case.
+//!
+//! `char::to_lowercase` is close to case folding but not the same: it leaves
+//! `ß` and the final sigma `ς` alone, for example, so "STRASSE" would never
+//! match "straße". Folding maps every character to a canonical caseless form
+//! that may be several characters long, and two strings are equal ignoring
+//! case exactly when their foldings are equal.
+
+use std::ops::Range;
+
+/// The case folding of a single character: at most three characters, kept
+/// inline so that folding never allocates.
+pub struct Folded {
+    chars: [char; 3],
+    len: usize,
+    next: usize,
+}
+
+impl Iterator for Folded {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars[..self.len].get(self.next).copied();
+        self.next += 1;
+        c
+    }
+}
+
+/// Folds the case of `c`.
+pub fn fold_char(c: char) -> Folded {
+    let mut folded = Folded {
+        chars: ['\0'; 3],
+        len: 0,
+        next: 0,
+    };
+    let mut push = |c| {
+        folded.chars[folded.len] = c;
+        folded.len += 1;
+    };
+
+    if c.is_ascii() {
+        push(c.to_ascii_lowercase());
+    } else {
+        match SPECIAL_FOLDS.binary_search_by_key(&c, |&(from, _)| from) {
+            Ok(index) => SPECIAL_FOLDS[index].1.chars().for_each(&mut push),
+            Err(_) => c.to_lowercase().for_each(&mut push),
+        }
+    }
+
+    folded
+}
+
+/// Folds the case of every character in `s`.
+pub fn fold(s: &str) -> Vec<char> {
+    s.chars().flat_map(fold_char).collect()
+}
+
+/// Returns the byte range of every non-overlapping occurrence of `query` in
+/// `line`, ignoring case. `query` must already be folded.
+///
+/// Matches always start and end on character boundaries of `line`: "s" does
+/// not match half of "ß".
+pub fn find_all(query: &[char], line: &str) -> Vec<Range<usize>> {
+    if query.is_empty() {
+        // Like `str::contains`, an empty query matches every line.
+        return std::iter::once(0..0).collect();
+    }
+
+    let mut found = Vec::new();
+    let mut start = 0;
+
+    while let Some(next) = find_at(query, line, start) {
+        start = next.end;
+        found.push(next);
+    }
+
+    found
+}
+
+/// Returns the first occurrence of `query` in `line` that starts at or after
+/// byte `start`, ignoring case. `query` must already be folded.
+pub fn find_at(query: &[char], line: &str, mut start: usize) -> Option<Range<usize>> {
+    if query.is_empty() {
+        return Some(start..start);
+    }
+
+    while start < line.len() {
+        if let Some(end) = match_at(query, line, start) {
+            return Some(start..end);
+        }
+        start += line[start..].chars().next().map_or(1, char::len_utf8);
+    }
+
+    None
+}
+
+/// Like `find_all`, but for any of several queries, taking the longest
+/// where more than one matches at the same place.
+pub fn find_all_of(queries: &[Vec<char>], line: &str) -> Vec<Range<usize>> {
+    let mut found = Vec::new();
+    let mut start = 0;
+
+    while let Some(next) = find_any_at(queries, line, start) {
+        start = next.end;
+        if next.is_empty() {
+            // An empty query matches everywhere, so skip on to the next
+            // character rather than finding the same place again.
+            if start == line.len() {
+                found.push(next);
+                break;
+            }
+            start += line[start..].chars().next().map_or(1, char::len_utf8);
+        }
+        found.push(next);
+    }
+
+    found
+}
+
+/// Like `find_at`, but for any of several queries, taking the longest where
+/// more than one matches at the same place.
+pub fn find_any_at(queries: &[Vec<char>], line: &str, mut start: usize) -> Option<Range<usize>> {
+    while start <= line.len() {
+        let end = queries
+            .iter()
+            .filter_map(|query| match query.is_empty() {
+                true => Some(start),
+                false => match_at(query, line, start),
+            })
+            .max();
+        if let Some(end) = end {
+            return Some(start..end);
+        }
+        start += line[start..].chars().next().map_or(1, char::len_utf8);
+    }
+
+    None
+}
+
+/// If `query` matches `line` starting at byte `start`, returns where the
+/// match ends.
+fn match_at(query: &[char], line: &str, start: usize) -> Option<usize> {
+    let mut expected = query.iter();
+
+    for (offset, c) in line[start..].char_indices() {
+        for folded in fold_char(c) {
+            if expected.next() != Some(&folded) {
+                return None;
+            }
+        }
+
+        if expected.len() == 0 {
+            return Some(start + offset + c.len_utf8());
+        }
+    }
+
+    None
+}
+
+/// Every character whose full case folding, the C and F mappings of the
+/// Unicode Character Database's CaseFolding.txt, differs from what
+/// `char::to_lowercase` gives. Sorted by character, for binary search.
+///
+/// Most are characters folded to something other than their lowercase form,
+/// or to several characters. Cherokee is the odd one out: it folds to
+/// uppercase, since most of its lowercase letters were added to Unicode
+/// later.
+#[rustfmt::skip]
+const SPECIAL_FOLDS: &[(char, &str)] = &[
+    ('\u{B5}', "\u{3BC}"), // MICRO SIGN
+    ('\u{DF}', "ss"), // LATIN SMALL LETTER SHARP S
+    ('\u{149}', "\u{2BC}n"), // LATIN SMALL LETTER N PRECEDED BY APOSTROPHE
+    ('\u{17F}', "s"), // LATIN SMALL LETTER LONG S
+    ('\u{1F0}', "j\u{30C}"), // LATIN SMALL LETTER J WITH CARON
+    ('\u{345}', "\u{3B9}"), // COMBINING GREEK YPOGEGRAMMENI
+    ('\u{390}', "\u{3B9}\u{308}\u{301}"), // GREEK SMALL LETTER IOTA WITH DIALYTIKA AND TONOS
+    ('\u{3B0}', "\u{3C5}\u{308}\u{301}"), // GREEK SMALL LETTER UPSILON WITH DIALYTIKA AND TONOS
+    ('\u{3C2}', "\u{3C3}"), // GREEK SMALL LETTER FINAL SIGMA
+    ('\u{3D0}', "\u{3B2}"), // GREEK BETA SYMBOL
+    ('\u{3D1}', "\u{3B8}"), // GREEK THETA SYMBOL
+    ('\u{3D5}', "\u{3C6}"), // GREEK PHI SYMBOL
+    ('\u{3D6}', "\u{3C0}"), // GREEK PI SYMBOL
+    ('\u{3F0}', "\u{3BA}"), // GREEK KAPPA SYMBOL
+    ('\u{3F1}', "\u{3C1}"), // GREEK RHO SYMBOL
+    ('\u{3F5}', "\u{3B5}"), // GREEK LUNATE EPSILON SYMBOL
+    ('\u{587}', "\u{565}\u{582}"), // ARMENIAN SMALL LIGATURE ECH YIWN
+    ('\u{13A0}', "\u{13A0}"), // CHEROKEE LETTER A
+    ('\u{13A1}', "\u{13A1}"), // CHEROKEE LETTER E
+    ('\u{13A2}', "\u{13A2}"), // CHEROKEE LETTER I
+    ('\u{13A3}', "\u{13A3}"), // CHEROKEE LETTER O
+    ('\u{13A4}', "\u{13A4}"), // CHEROKEE LETTER U
+    ('\u{13A5}', "\u{13A5}"), // CHEROKEE LETTER V
+    ('\u{13A6}', "\u{13A6}"), // CHEROKEE LETTER GA
+    ('\u{13A7}', "\u{13A7}"), // CHEROKEE LETTER KA
+    ('\u{13A8}', "\u{13A8}"), // CHEROKEE LETTER GE
+    ('\u{13A9}', "\u{13A9}"), // CHEROKEE LETTER GI
+    ('\u{13AA}', "\u{13AA}"), // CHEROKEE LETTER GO
+    ('\u{13AB}', "\u{13AB}"), // CHEROKEE LETTER GU
+    ('\u{13AC}', "\u{13AC}"), // CHEROKEE LETTER GV
+    ('\u{13AD}', "\u{13AD}"), // CHEROKEE LETTER HA
+    ('\u{13AE}', "\u{13AE}"), // CHEROKEE LETTER HE
+    ('\u{13AF}', "\u{13AF}"), // CHEROKEE LETTER HI
+    ('\u{13B0}', "\u{13B0}"), // CHEROKEE LETTER HO
+    ('\u{13B1}', "\u{13B1}"), // CHEROKEE LETTER HU
+    ('\u{13B2}', "\u{13B2}"), // CHEROKEE LETTER HV
+    ('\u{13B3}', "\u{13B3}"), // CHEROKEE LETTER LA
+    ('\u{13B4}', "\u{13B4}"), // CHEROKEE LETTER LE
+    ('\u{13B5}', "\u{13B5}"), // CHEROKEE LETTER LI
+    ('\u{13B6}', "\u{13B6}"), // CHEROKEE LETTER LO
+    ('\u{13B7}', "\u{13B7}"), // CHEROKEE LETTER LU
+    ('\u{13B8}', "\u{13B8}"), // CHEROKEE LETTER LV
+    ('\u{13B9}', "\u{13B9}"), // CHEROKEE LETTER MA
+    ('\u{13BA}', "\u{13BA}"), // CHEROKEE LETTER ME
+    ('\u{13BB}', "\u{13BB}"), // CHEROKEE LETTER MI
+    ('\u{13BC}', "\u{13BC}"), // CHEROKEE LETTER MO
+    ('\u{13BD}', "\u{13BD}"), // CHEROKEE LETTER MU
+    ('\u{13BE}', "\u{13BE}"), // CHEROKEE LETTER NA
+    ('\u{13BF}', "\u{13BF}"), // CHEROKEE LETTER HNA
+    ('\u{13C0}', "\u{13C0}"), // CHEROKEE LETTER NAH
+    ('\u{13C1}', "\u{13C1}"), // CHEROKEE LETTER NE
+    ('\u{13C2}', "\u{13C2}"), // CHEROKEE LETTER NI
+    ('\u{13C3}', "\u{13C3}"), // CHEROKEE LETTER NO
+    ('\u{13C4}', "\u{13C4}"), // CHEROKEE LETTER NU
+    ('\u{13C5}', "\u{13C5}"), // CHEROKEE LETTER NV
+    ('\u{13C6}', "\u{13C6}"), // CHEROKEE LETTER QUA
+    ('\u{13C7}', "\u{13C7}"), // CHEROKEE LETTER QUE
+    ('\u{13C8}', "\u{13C8}"), // CHEROKEE LETTER QUI
+    ('\u{13C9}', "\u{13C9}"), // CHEROKEE LETTER QUO
+    ('\u{13CA}', "\u{13CA}"), // CHEROKEE LETTER QUU
+    ('\u{13CB}', "\u{13CB}"), // CHEROKEE LETTER QUV
+    ('\u{13CC}', "\u{13CC}"), // CHEROKEE LETTER SA
+    ('\u{13CD}', "\u{13CD}"), // CHEROKEE LETTER S
+    ('\u{13CE}', "\u{13CE}"), // CHEROKEE LETTER SE
+    ('\u{13CF}', "\u{13CF}"), // CHEROKEE LETTER SI
+    ('\u{13D0}', "\u{13D0}"), // CHEROKEE LETTER SO
+    ('\u{13D1}', "\u{13D1}"), // CHEROKEE LETTER SU
+    ('\u{13D2}', "\u{13D2}"), // CHEROKEE LETTER SV
+    ('\u{13D3}', "\u{13D3}"), // CHEROKEE LETTER DA
+    ('\u{13D4}', "\u{13D4}"), // CHEROKEE LETTER TA
+    ('\u{13D5}', "\u{13D5}"), // CHEROKEE LETTER DE
+    ('\u{13D6}', "\u{13D6}"), // CHEROKEE LETTER TE
+    ('\u{13D7}', "\u{13D7}"), // CHEROKEE LETTER DI
+    ('\u{13D8}', "\u{13D8}"), // CHEROKEE LETTER TI
+    ('\u{13D9}', "\u{13D9}"), // CHEROKEE LETTER DO
+    ('\u{13DA}', "\u{13DA}"), // CHEROKEE LETTER DU
+    ('\u{13DB}', "\u{13DB}"), // CHEROKEE LETTER DV
+    ('\u{13DC}', "\u{13DC}"), // CHEROKEE LETTER DLA
+    ('\u{13DD}', "\u{13DD}"), // CHEROKEE LETTER TLA
+    ('\u{13DE}', "\u{13DE}"), // CHEROKEE LETTER TLE
+    ('\u{13DF}', "\u{13DF}"), // CHEROKEE LETTER TLI
+    ('\u{13E0}', "\u{13E0}"), // CHEROKEE LETTER TLO
+    ('\u{13E1}', "\u{13E1}"), // CHEROKEE LETTER TLU
+    ('\u{13E2}', "\u{13E2}"), // CHEROKEE LETTER TLV
+    ('\u{13E3}', "\u{13E3}"), // CHEROKEE LETTER TSA
+    ('\u{13E4}', "\u{13E4}"), // CHEROKEE LETTER TSE
+    ('\u{13E5}', "\u{13E5}"), // CHEROKEE LETTER TSI
+    ('\u{13E6}', "\u{13E6}"), // CHEROKEE LETTER TSO
+    ('\u{13E7}', "\u{13E7}"), // CHEROKEE LETTER TSU
+    ('\u{13E8}', "\u{13E8}"), // CHEROKEE LETTER TSV
+    ('\u{13E9}', "\u{13E9}"), // CHEROKEE LETTER WA
+    ('\u{13EA}', "\u{13EA}"), // CHEROKEE LETTER WE
+    ('\u{13EB}', "\u{13EB}"), // CHEROKEE LETTER WI
+    ('\u{13EC}', "\u{13EC}"), // CHEROKEE LETTER WO
+    ('\u{13ED}', "\u{13ED}"), // CHEROKEE LETTER WU
+    ('\u{13EE}', "\u{13EE}"), // CHEROKEE LETTER WV
+    ('\u{13EF}', "\u{13EF}"), // CHEROKEE LETTER YA
+    ('\u{13F0}', "\u{13F0}"), // CHEROKEE LETTER YE
+    ('\u{13F1}', "\u{13F1}"), // CHEROKEE LETTER YI
+    ('\u{13F2}', "\u{13F2}"), // CHEROKEE LETTER YO
+    ('\u{13F3}', "\u{13F3}"), // CHEROKEE LETTER YU
+    ('\u{13F4}', "\u{13F4}"), // CHEROKEE LETTER YV
+    ('\u{13F5}', "\u{13F5}"), // CHEROKEE LETTER MV
+    ('\u{13F8}', "\u{13F0}"), // CHEROKEE SMALL LETTER YE
+    ('\u{13F9}', "\u{13F1}"), // CHEROKEE SMALL LETTER YI
+    ('\u{13FA}', "\u{13F2}"), // CHEROKEE SMALL LETTER YO
+    ('\u{13FB}', "\u{13F3}"), // CHEROKEE SMALL LETTER YU
+    ('\u{13FC}', "\u{13F4}"), // CHEROKEE SMALL LETTER YV
+    ('\u{13FD}', "\u{13F5}"), // CHEROKEE SMALL LETTER MV
+    ('\u{1C80}', "\u{432}"), // CYRILLIC SMALL LETTER ROUNDED VE
+    ('\u{1C81}', "\u{434}"), // CYRILLIC SMALL LETTER LONG-LEGGED DE
+    ('\u{1C82}', "\u{43E}"), // CYRILLIC SMALL LETTER NARROW O
+    ('\u{1C83}', "\u{441}"), // CYRILLIC SMALL LETTER WIDE ES
+    ('\u{1C84}', "\u{442}"), // CYRILLIC SMALL LETTER TALL TE
+    ('\u{1C85}', "\u{442}"), // CYRILLIC SMALL LETTER THREE-LEGGED TE
+    ('\u{1C86}', "\u{44A}"), // CYRILLIC SMALL LETTER TALL HARD SIGN
+    ('\u{1C87}', "\u{463}"), // CYRILLIC SMALL LETTER TALL YAT
+    ('\u{1C88}', "\u{A64B}"), // CYRILLIC SMALL LETTER UNBLENDED UK
+    ('\u{1E96}', "h\u{331}"), // LATIN SMALL LETTER H WITH LINE BELOW
+    ('\u{1E97}', "t\u{308}"), // LATIN SMALL LETTER T WITH DIAERESIS
+    ('\u{1E98}', "w\u{30A}"), // LATIN SMALL LETTER W WITH RING ABOVE
+    ('\u{1E99}', "y\u{30A}"), // LATIN SMALL LETTER Y WITH RING ABOVE
+    ('\u{1E9A}', "a\u{2BE}"), // LATIN SMALL LETTER A WITH RIGHT HALF RING
+    ('\u{1E9B}', "\u{1E61}"), // LATIN SMALL LETTER LONG S WITH DOT ABOVE
+    ('\u{1E9E}', "ss"), // LATIN CAPITAL LETTER SHARP S
+    ('\u{1F50}', "\u{3C5}\u{313}"), // GREEK SMALL LETTER UPSILON WITH PSILI
+    ('\u{1F52}', "\u{3C5}\u{313}\u{300}"), // GREEK SMALL LETTER UPSILON WITH PSILI AND VARIA
+    ('\u{1F54}', "\u{3C5}\u{313}\u{301}"), // GREEK SMALL LETTER UPSILON WITH PSILI AND OXIA
+    ('\u{1F56}', "\u{3C5}\u{313}\u{342}"), // GREEK SMALL LETTER UPSILON WITH PSILI AND PERISPOMENI
+    ('\u{1F80}', "\u{1F00}\u{3B9}"), // GREEK SMALL LETTER ALPHA WITH PSILI AND YPOGEGRAMMENI
+    ('\u{1F81}', "\u{1F01}\u{3B9}"), // GREEK SMALL LETTER ALPHA WITH DASIA AND YPOGEGRAMMENI
+    ('\u{1F82}', "\u{1F02}\u{3B9}"), // GREEK SMALL LETTER ALPHA WITH PSILI AND VARIA AND YPOGEGRAMMENI
+    ('\u{1F83}', "\u{1F03}\u{3B9}"), // GREEK SMALL LETTER ALPHA WITH DASIA AND VARIA AND YPOGEGRAMMENI
+    ('\u{1F84}', "\u{1F04}\u{3B9}"), // GREEK SMALL LETTER ALPHA WITH PSILI AND OXIA AND YPOGEGRAMMENI
+    ('\u{1F85}', "\u{1F05}\u{3B9}"), // GREEK SMALL LETTER ALPHA WITH DASIA AND OXIA AND YPOGEGRAMMENI
+    ('\u{1F86}', "\u{1F06}\u{3B9}"), // GREEK SMALL LETTER ALPHA WITH PSILI AND PERISPOMENI AND YPOGEGRAMMENI
+    ('\u{1F87}', "\u{1F07}\u{3B9}"), // GREEK SMALL LETTER ALPHA WITH DASIA AND PERISPOMENI AND YPOGEGRAMMENI
+    ('\u{1F88}', "\u{1F00}\u{3B9}"), // GREEK CAPITAL LETTER ALPHA WITH PSILI AND PROSGEGRAMMENI
+    ('\u{1F89}', "\u{1F01}\u{3B9}"), // GREEK CAPITAL LETTER ALPHA WITH DASIA AND PROSGEGRAMMENI
+    ('\u{1F8A}', "\u{1F02}\u{3B9}"), // GREEK CAPITAL LETTER ALPHA WITH PSILI AND VARIA AND PROSGEGRAMMENI
+    ('\u{1F8B}', "\u{1F03}\u{3B9}"), // GREEK CAPITAL LETTER ALPHA WITH DASIA AND VARIA AND PROSGEGRAMMENI
+    ('\u{1F8C}', "\u{1F04}\u{3B9}"), // GREEK CAPITAL LETTER ALPHA WITH PSILI AND OXIA AND PROSGEGRAMMENI
+    ('\u{1F8D}', "\u{1F05}\u{3B9}"), // GREEK CAPITAL LETTER ALPHA WITH DASIA AND OXIA AND PROSGEGRAMMENI
+    ('\u{1F8E}', "\u{1F06}\u{3B9}"), // GREEK CAPITAL LETTER ALPHA WITH PSILI AND PERISPOMENI AND PROSGEGRAMMENI
+    ('\u{1F8F}', "\u{1F07}\u{3B9}"), // GREEK CAPITAL LETTER ALPHA WITH DASIA AND PERISPOMENI AND PROSGEGRAMMENI
+    ('\u{1F90}', "\u{1F20}\u{3B9}"), // GREEK SMALL LETTER ETA WITH PSILI AND YPOGEGRAMMENI
+    ('\u{1F91}', "\u{1F21}\u{3B9}"), // GREEK SMALL LETTER ETA WITH DASIA AND YPOGEGRAMMENI
+    ('\u{1F92}', "\u{1F22}\u{3B9}"), // GREEK SMALL LETTER ETA WITH PSILI AND VARIA AND YPOGEGRAMMENI
+    ('\u{1F93}', "\u{1F23}\u{3B9}"), // GREEK SMALL LETTER ETA WITH DASIA AND VARIA AND YPOGEGRAMMENI
+    ('\u{1F94}', "\u{1F24}\u{3B9}"), // GREEK SMALL LETTER ETA WITH PSILI AND OXIA AND YPOGEGRAMMENI
+    ('\u{1F95}', "\u{1F25}\u{3B9}"), // GREEK SMALL LETTER ETA WITH DASIA AND OXIA AND YPOGEGRAMMENI
+    ('\u{1F96}', "\u{1F26}\u{3B9}"), // GREEK SMALL LETTER ETA WITH PSILI AND PERISPOMENI AND YPOGEGRAMMENI
+    ('\u{1F97}', "\u{1F27}\u{3B9}"), // GREEK SMALL LETTER ETA WITH DASIA AND PERISPOMENI AND YPOGEGRAMMENI
+    ('\u{1F98}', "\u{1F20}\u{3B9}"), // GREEK CAPITAL LETTER ETA WITH PSILI AND PROSGEGRAMMENI
+    ('\u{1F99}', "\u{1F21}\u{3B9}"), // GREEK CAPITAL LETTER ETA WITH DASIA AND PROSGEGRAMMENI
+    ('\u{1F9A}', "\u{1F22}\u{3B9}"), // GREEK CAPITAL LETTER ETA WITH PSILI AND VARIA AND PROSGEGRAMMENI
+    ('\u{1F9B}', "\u{1F23}\u{3B9}"), // GREEK CAPITAL LETTER ETA WITH DASIA AND VARIA AND PROSGEGRAMMENI
+    ('\u{1F9C}', "\u{1F24}\u{3B9}"), // GREEK CAPITAL LETTER ETA WITH PSILI AND OXIA AND PROSGEGRAMMENI
+    ('\u{1F9D}', "\u{1F25}\u{3B9}"), // GREEK CAPITAL LETTER ETA WITH DASIA AND OXIA AND PROSGEGRAMMENI
+    ('\u{1F9E}', "\u{1F26}\u{3B9}"), // GREEK CAPITAL LETTER ETA WITH PSILI AND PERISPOMENI AND PROSGEGRAMMENI
+    ('\u{1F9F}', "\u{1F27}\u{3B9}"), // GREEK CAPITAL LETTER ETA WITH DASIA AND PERISPOMENI AND PROSGEGRAMMENI
+    ('\u{1FA0}', "\u{1F60}\u{3B9}"), // GREEK SMALL LETTER OMEGA WITH PSILI AND YPOGEGRAMMENI
+    ('\u{1FA1}', "\u{1F61}\u{3B9}"), // GREEK SMALL LETTER OMEGA WITH DASIA AND YPOGEGRAMMENI
+    ('\u{1FA2}', "\u{1F62}\u{3B9}"), // GREEK SMALL LETTER OMEGA WITH PSILI AND VARIA AND YPOGEGRAMMENI
+    ('\u{1FA3}', "\u{1F63}\u{3B9}"), // GREEK SMALL LETTER OMEGA WITH DASIA AND VARIA AND YPOGEGRAMMENI
+    ('\u{1FA4}', "\u{1F64}\u{3B9}"), // GREEK SMALL LETTER OMEGA WITH PSILI AND OXIA AND YPOGEGRAMMENI
+    ('\u{1FA5}', "\u{1F65}\u{3B9}"), // GREEK SMALL LETTER OMEGA WITH DASIA AND OXIA AND YPOGEGRAMMENI
+    ('\u{1FA6}', "\u{1F66}\u{3B9}"), // GREEK SMALL LETTER OMEGA WITH PSILI AND PERISPOMENI AND YPOGEGRAMMENI
+    ('\u{1FA7}', "\u{1F67}\u{3B9}"), // GREEK SMALL LETTER OMEGA WITH DASIA AND PERISPOMENI AND YPOGEGRAMMENI
+    ('\u{1FA8}', "\u{1F60}\u{3B9}"), // GREEK CAPITAL LETTER OMEGA WITH PSILI AND PROSGEGRAMMENI
+    ('\u{1FA9}', "\u{1F61}\u{3B9}"), // GREEK CAPITAL LETTER OMEGA WITH DASIA AND PROSGEGRAMMENI
+    ('\u{1FAA}', "\u{1F62}\u{3B9}"), // GREEK CAPITAL LETTER OMEGA WITH PSILI AND VARIA AND PROSGEGRAMMENI
+    ('\u{1FAB}', "\u{1F63}\u{3B9}"), // GREEK CAPITAL LETTER OMEGA WITH DASIA AND VARIA AND PROSGEGRAMMENI
+    ('\u{1FAC}', "\u{1F64}\u{3B9}"), // GREEK CAPITAL LETTER OMEGA WITH PSILI AND OXIA AND PROSGEGRAMMENI
+    ('\u{1FAD}', "\u{1F65}\u{3B9}"), // GREEK CAPITAL LETTER OMEGA WITH DASIA AND OXIA AND PROSGEGRAMMENI
+    ('\u{1FAE}', "\u{1F66}\u{3B9}"), // GREEK CAPITAL LETTER OMEGA WITH PSILI AND PERISPOMENI AND PROSGEGRAMMENI
+    ('\u{1FAF}', "\u{1F67}\u{3B9}"), // GREEK CAPITAL LETTER OMEGA WITH DASIA AND PERISPOMENI AND PROSGEGRAMMENI
+    ('\u{1FB2}', "\u{1F70}\u{3B9}"), // GREEK SMALL LETTER ALPHA WITH VARIA AND YPOGEGRAMMENI
+    ('\u{1FB3}', "\u{3B1}\u{3B9}"), // GREEK SMALL LETTER ALPHA WITH YPOGEGRAMMENI
+    ('\u{1FB4}', "\u{3AC}\u{3B9}"), // GREEK SMALL LETTER ALPHA WITH OXIA AND YPOGEGRAMMENI
+    ('\u{1FB6}', "\u{3B1}\u{342}"), // GREEK SMALL LETTER ALPHA WITH PERISPOMENI
+    ('\u{1FB7}', "\u{3B1}\u{342}\u{3B9}"), // GREEK SMALL LETTER ALPHA WITH PERISPOMENI AND YPOGEGRAMMENI
+    ('\u{1FBC}', "\u{3B1}\u{3B9}"), // GREEK CAPITAL LETTER ALPHA WITH PROSGEGRAMMENI
+    ('\u{1FBE}', "\u{3B9}"), // GREEK PROSGEGRAMMENI
+    ('\u{1FC2}', "\u{1F74}\u{3B9}"), // GREEK SMALL LETTER ETA WITH VARIA AND YPOGEGRAMMENI
+    ('\u{1FC3}', "\u{3B7}\u{3B9}"), // GREEK SMALL LETTER ETA WITH YPOGEGRAMMENI
+    ('\u{1FC4}', "\u{3AE}\u{3B9}"), // GREEK SMALL LETTER ETA WITH OXIA AND YPOGEGRAMMENI
+    ('\u{1FC6}', "\u{3B7}\u{342}"), // GREEK SMALL LETTER ETA WITH PERISPOMENI
+    ('\u{1FC7}', "\u{3B7}\u{342}\u{3B9}"), // GREEK SMALL LETTER ETA WITH PERISPOMENI AND YPOGEGRAMMENI
+    ('\u{1FCC}', "\u{3B7}\u{3B9}"), // GREEK CAPITAL LETTER ETA WITH PROSGEGRAMMENI
+    ('\u{1FD2}', "\u{3B9}\u{308}\u{300}"), // GREEK SMALL LETTER IOTA WITH DIALYTIKA AND VARIA
+    ('\u{1FD3}', "\u{3B9}\u{308}\u{301}"), // GREEK SMALL LETTER IOTA WITH DIALYTIKA AND OXIA
+    ('\u{1FD6}', "\u{3B9}\u{342}"), // GREEK SMALL LETTER IOTA WITH PERISPOMENI
+    ('\u{1FD7}', "\u{3B9}\u{308}\u{342}"), // GREEK SMALL LETTER IOTA WITH DIALYTIKA AND PERISPOMENI
+    ('\u{1FE2}', "\u{3C5}\u{308}\u{300}"), // GREEK SMALL LETTER UPSILON WITH DIALYTIKA AND VARIA
+    ('\u{1FE3}', "\u{3C5}\u{308}\u{301}"), // GREEK SMALL LETTER UPSILON WITH DIALYTIKA AND OXIA
+    ('\u{1FE4}', "\u{3C1}\u{313}"), // GREEK SMALL LETTER RHO WITH PSILI
+    ('\u{1FE6}', "\u{3C5}\u{342}"), // GREEK SMALL LETTER UPSILON WITH PERISPOMENI
+    ('\u{1FE7}', "\u{3C5}\u{308}\u{342}"), // GREEK SMALL LETTER UPSILON WITH DIALYTIKA AND PERISPOMENI
+    ('\u{1FF2}', "\u{1F7C}\u{3B9}"), // GREEK SMALL LETTER OMEGA WITH VARIA AND YPOGEGRAMMENI
+    ('\u{1FF3}', "\u{3C9}\u{3B9}"), // GREEK SMALL LETTER OMEGA WITH YPOGEGRAMMENI
+    ('\u{1FF4}', "\u{3CE}\u{3B9}"), // GREEK SMALL LETTER OMEGA WITH OXIA AND YPOGEGRAMMENI
+    ('\u{1FF6}', "\u{3C9}\u{342}"), // GREEK SMALL LETTER OMEGA WITH PERISPOMENI
+    ('\u{1FF7}', "\u{3C9}\u{342}\u{3B9}"), // GREEK SMALL LETTER OMEGA WITH PERISPOMENI AND YPOGEGRAMMENI
+    ('\u{1FFC}', "\u{3C9}\u{3B9}"), // GREEK CAPITAL LETTER OMEGA WITH PROSGEGRAMMENI
+    ('\u{AB70}', "\u{13A0}"), // CHEROKEE SMALL LETTER A
+    ('\u{AB71}', "\u{13A1}"), // CHEROKEE SMALL LETTER E
+    ('\u{AB72}', "\u{13A2}"), // CHEROKEE SMALL LETTER I
+    ('\u{AB73}', "\u{13A3}"), // CHEROKEE SMALL LETTER O
+    ('\u{AB74}', "\u{13A4}"), // CHEROKEE SMALL LETTER U
+    ('\u{AB75}', "\u{13A5}"), // CHEROKEE SMALL LETTER V
+    ('\u{AB76}', "\u{13A6}"), // CHEROKEE SMALL LETTER GA
+    ('\u{AB77}', "\u{13A7}"), // CHEROKEE SMALL LETTER KA
+    ('\u{AB78}', "\u{13A8}"), // CHEROKEE SMALL LETTER GE
+    ('\u{AB79}', "\u{13A9}"), // CHEROKEE SMALL LETTER GI
+    ('\u{AB7A}', "\u{13AA}"), // CHEROKEE SMALL LETTER GO
+    ('\u{AB7B}', "\u{13AB}"), // CHEROKEE SMALL LETTER GU
+    ('\u{AB7C}', "\u{13AC}"), // CHEROKEE SMALL LETTER GV
+    ('\u{AB7D}', "\u{13AD}"), // CHEROKEE SMALL LETTER HA
+    ('\u{AB7E}', "\u{13AE}"), // CHEROKEE SMALL LETTER HE
+    ('\u{AB7F}', "\u{13AF}"), // CHEROKEE SMALL LETTER HI
+    ('\u{AB80}', "\u{13B0}"), // CHEROKEE SMALL LETTER HO
+    ('\u{AB81}', "\u{13B1}"), // CHEROKEE SMALL LETTER HU
+    ('\u{AB82}', "\u{13B2}"), // CHEROKEE SMALL LETTER HV
+    ('\u{AB83}', "\u{13B3}"), // CHEROKEE SMALL LETTER LA
+    ('\u{AB84}', "\u{13B4}"), // CHEROKEE SMALL LETTER LE
+    ('\u{AB85}', "\u{13B5}"), // CHEROKEE SMALL LETTER LI
+    ('\u{AB86}', "\u{13B6}"), // CHEROKEE SMALL LETTER LO
+    ('\u{AB87}', "\u{13B7}"), // CHEROKEE SMALL LETTER LU
+    ('\u{AB88}', "\u{13B8}"), // CHEROKEE SMALL LETTER LV
+    ('\u{AB89}', "\u{13B9}"), // CHEROKEE SMALL LETTER MA
+    ('\u{AB8A}', "\u{13BA}"), // CHEROKEE SMALL LETTER ME
+    ('\u{AB8B}', "\u{13BB}"), // CHEROKEE SMALL LETTER MI
+    ('\u{AB8C}', "\u{13BC}"), // CHEROKEE SMALL LETTER MO
+    ('\u{AB8D}', "\u{13BD}"), // CHEROKEE SMALL LETTER MU
+    ('\u{AB8E}', "\u{13BE}"), // CHEROKEE SMALL LETTER NA
+    ('\u{AB8F}', "\u{13BF}"), // CHEROKEE SMALL LETTER HNA
+    ('\u{AB90}', "\u{13C0}"), // CHEROKEE SMALL LETTER NAH
+    ('\u{AB91}', "\u{13C1}"), // CHEROKEE SMALL LETTER NE
+    ('\u{AB92}', "\u{13C2}"), // CHEROKEE SMALL LETTER NI
+    ('\u{AB93}', "\u{13C3}"), // CHEROKEE SMALL LETTER NO
+    ('\u{AB94}', "\u{13C4}"), // CHEROKEE SMALL LETTER NU
+    ('\u{AB95}', "\u{13C5}"), // CHEROKEE SMALL LETTER NV
+    ('\u{AB96}', "\u{13C6}"), // CHEROKEE SMALL LETTER QUA
+    ('\u{AB97}', "\u{13C7}"), // CHEROKEE SMALL LETTER QUE
+    ('\u{AB98}', "\u{13C8}"), // CHEROKEE SMALL LETTER QUI
+    ('\u{AB99}', "\u{13C9}"), // CHEROKEE SMALL LETTER QUO
+    ('\u{AB9A}', "\u{13CA}"), // CHEROKEE SMALL LETTER QUU
+    ('\u{AB9B}', "\u{13CB}"), // CHEROKEE SMALL LETTER QUV
+    ('\u{AB9C}', "\u{13CC}"), // CHEROKEE SMALL LETTER SA
+    ('\u{AB9D}', "\u{13CD}"), // CHEROKEE SMALL LETTER S
+    ('\u{AB9E}', "\u{13CE}"), // CHEROKEE SMALL LETTER SE
+    ('\u{AB9F}', "\u{13CF}"), // CHEROKEE SMALL LETTER SI
+    ('\u{ABA0}', "\u{13D0}"), // CHEROKEE SMALL LETTER SO
+    ('\u{ABA1}', "\u{13D1}"), // CHEROKEE SMALL LETTER SU
+    ('\u{ABA2}', "\u{13D2}"), // CHEROKEE SMALL LETTER SV
+    ('\u{ABA3}', "\u{13D3}"), // CHEROKEE SMALL LETTER DA
+    ('\u{ABA4}', "\u{13D4}"), // CHEROKEE SMALL LETTER TA
+    ('\u{ABA5}', "\u{13D5}"), // CHEROKEE SMALL LETTER DE
+    ('\u{ABA6}', "\u{13D6}"), // CHEROKEE SMALL LETTER TE
+    ('\u{ABA7}', "\u{13D7}"), // CHEROKEE SMALL LETTER DI
+    ('\u{ABA8}', "\u{13D8}"), // CHEROKEE SMALL LETTER TI
+    ('\u{ABA9}', "\u{13D9}"), // CHEROKEE SMALL LETTER DO
+    ('\u{ABAA}', "\u{13DA}"), // CHEROKEE SMALL LETTER DU
+    ('\u{ABAB}', "\u{13DB}"), // CHEROKEE SMALL LETTER DV
+    ('\u{ABAC}', "\u{13DC}"), // CHEROKEE SMALL LETTER DLA
+    ('\u{ABAD}', "\u{13DD}"), // CHEROKEE SMALL LETTER TLA
+    ('\u{ABAE}', "\u{13DE}"), // CHEROKEE SMALL LETTER TLE
+    ('\u{ABAF}', "\u{13DF}"), // CHEROKEE SMALL LETTER TLI
+    ('\u{ABB0}', "\u{13E0}"), // CHEROKEE SMALL LETTER TLO
+    ('\u{ABB1}', "\u{13E1}"), // CHEROKEE SMALL LETTER TLU
+    ('\u{ABB2}', "\u{13E2}"), // CHEROKEE SMALL LETTER TLV
+    ('\u{ABB3}', "\u{13E3}"), // CHEROKEE SMALL LETTER TSA
+    ('\u{ABB4}', "\u{13E4}"), // CHEROKEE SMALL LETTER TSE
+    ('\u{ABB5}', "\u{13E5}"), // CHEROKEE SMALL LETTER TSI
+    ('\u{ABB6}', "\u{13E6}"), // CHEROKEE SMALL LETTER TSO
+    ('\u{ABB7}', "\u{13E7}"), // CHEROKEE SMALL LETTER TSU
+    ('\u{ABB8}', "\u{13E8}"), // CHEROKEE SMALL LETTER TSV
+    ('\u{ABB9}', "\u{13E9}"), // CHEROKEE SMALL LETTER WA
+    ('\u{ABBA}', "\u{13EA}"), // CHEROKEE SMALL LETTER WE
+    ('\u{ABBB}', "\u{13EB}"), // CHEROKEE SMALL LETTER WI
+    ('\u{ABBC}', "\u{13EC}"), // CHEROKEE SMALL LETTER WO
+    ('\u{ABBD}', "\u{13ED}"), // CHEROKEE SMALL LETTER WU
+    ('\u{ABBE}', "\u{13EE}"), // CHEROKEE SMALL LETTER WV
+    ('\u{ABBF}', "\u{13EF}"), // CHEROKEE SMALL LETTER YA
+    ('\u{FB00}', "ff"), // LATIN SMALL LIGATURE FF
+    ('\u{FB01}', "fi"), // LATIN SMALL LIGATURE FI
+    ('\u{FB02}', "fl"), // LATIN SMALL LIGATURE FL
+    ('\u{FB03}', "ffi"), // LATIN SMALL LIGATURE FFI
+    ('\u{FB04}', "ffl"), // LATIN SMALL LIGATURE FFL
+    ('\u{FB05}', "st"), // LATIN SMALL LIGATURE LONG S T
+    ('\u{FB06}', "st"), // LATIN SMALL LIGATURE ST
+    ('\u{FB13}', "\u{574}\u{576}"), // ARMENIAN SMALL LIGATURE MEN NOW
+    ('\u{FB14}', "\u{574}\u{565}"), // ARMENIAN SMALL LIGATURE MEN ECH
+    ('\u{FB15}', "\u{574}\u{56B}"), // ARMENIAN SMALL LIGATURE MEN INI
+    ('\u{FB16}', "\u{57E}\u{576}"), // ARMENIAN SMALL LIGATURE VEW NOW
+    ('\u{FB17}', "\u{574}\u{56D}"), // ARMENIAN SMALL LIGATURE MEN XEH
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(query: &str, line: &str) -> Vec<Range<usize>> {
+        find_all(&fold(query), line)
+    }
+
+    #[test]
+    fn several_queries() {
+        let queries = [fold("ß"), fold("STRASSE"), fold("x")];
+        assert_eq!(vec![0..7, 9..10], find_all_of(&queries, "STRAßE, x"));
+        assert_eq!(vec![2..4], find_all_of(&queries, "Mass"));
+        // An empty query matches at every character, unless a longer query
+        // matches there.
+        let queries = [fold(""), fold("ss")];
+        assert_eq!(vec![0..0, 1..3, 3..3, 4..4], find_all_of(&queries, "aßs"));
+    }
+
+    #[test]
+    fn sharp_s_folds_to_ss() {
+        assert_eq!(vec![0..7], find("STRASSE", "straße"));
+        assert_eq!(vec![2..4], find("ẞ", "Maß"));
+        // Half of a folded character is not a match.
+        assert!(find("s", "ß").is_empty());
+    }
+
+    #[test]
+    fn final_sigma() {
+        assert_eq!(vec![0..14], find("ΣΊΣΥΦΟΣ", "σίσυφος"));
+    }
+
+    #[test]
+    fn dotted_and_dotless_i_stay_distinct() {
+        assert!(find("I", "ı").is_empty());
+        assert_eq!(vec![0..2], find("i\u{307}", "İ"));
+    }
+
+    #[test]
+    fn caseless_positions_survive_length_changes() {
+        // 'İ' is two bytes, but folds to three.
+        assert_eq!(vec![2..9], find("stanbul", "İstanbul"));
+    }
+
+    #[test]
+    fn micro_sign_and_iota_subscripts() {
+        assert_eq!(vec![0..2], find("Μ", "µ"));
+        assert_eq!(vec![0..2], find("µ", "Μ"));
+        assert_eq!(vec![0..2, 2..5], find("ι", "\u{345}\u{1FBE}"));
+        // With a capital iota subscript, ᾼ folds to the same as ᾳ: alpha
+        // followed by iota.
+        assert_eq!(vec![0..3], find("ᾳ", "ᾼ"));
+        assert_eq!(vec![0..3], find("αι", "ᾼ"));
+    }
+
+    #[test]
+    fn special_folds_are_sorted() {
+        assert!(SPECIAL_FOLDS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn matches_do_not_overlap() {
+        assert_eq!(vec![0..2, 2..4, 5..7], find("aA", "aaaa aa"));
+    }
+}
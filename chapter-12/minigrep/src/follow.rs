@@ -0,0 +1,287 @@
+//! Following a file as it grows, the way `tail -f` does.
+//!
+//! There's no portable way to be told when a file changes, so the file is
+//! polled instead: each poll reads whatever has been appended since the last
+//! one. Between polls the file may also have been truncated, as log files
+//! often are by `> app.log`, or replaced by a new file at the same path, as
+//! log rotation does. Either way, reading starts again from the top.
+//!
+//! Only UTF-8 text can be followed. A file that starts like a binary file, or
+//! with a BOM for another encoding, is refused rather than searched as
+//! garbage.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use memchr::memchr;
+use same_file::Handle;
+
+use crate::{encoding, walk, Match};
+
+/// How long to wait between polls.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How much of the file is read at once.
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// Something that happened to the file between polls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    /// The file got shorter, so it is being read again from the start.
+    Truncated,
+    /// A different file now has the same path, and is being read from the
+    /// start instead.
+    Replaced,
+}
+
+/// Reads the lines of a file as they are written.
+///
+/// Only complete lines are passed on: a line that is still being written is
+/// held back until its line terminator arrives, unless the file is replaced
+/// first, in which case it will never be finished.
+pub struct Follower {
+    path: PathBuf,
+    file: File,
+    handle: Handle,
+    /// Where the next line to be passed on starts in the file.
+    position: u64,
+    /// Bytes read after the last line terminator.
+    pending: Vec<u8>,
+    line_number: usize,
+}
+
+impl Follower {
+    pub fn open(path: &Path) -> io::Result<Follower> {
+        let file = File::open(path)?;
+        let handle = Handle::from_file(file.try_clone()?)?;
+
+        Ok(Follower {
+            path: path.to_path_buf(),
+            file,
+            handle,
+            position: 0,
+            pending: Vec::new(),
+            line_number: 0,
+        })
+    }
+
+    /// Passes every line written since the last poll to `each`, and reports
+    /// whether the file was truncated or replaced along the way. The first
+    /// poll reads the whole of the file's existing contents.
+    pub fn poll(
+        &mut self,
+        each: &mut impl FnMut(Match) -> io::Result<()>,
+    ) -> io::Result<Option<Change>> {
+        if self.file.metadata()?.len() < self.position {
+            self.file.seek(SeekFrom::Start(0))?;
+            self.restart();
+            self.read_available(each)?;
+            return Ok(Some(Change::Truncated));
+        }
+
+        self.read_available(each)?;
+
+        // While a file is being rotated, there may briefly be nothing at its
+        // path, in which case the old file is kept until the new one appears.
+        let replaced = match Handle::from_path(&self.path) {
+            Ok(handle) => handle != self.handle,
+            Err(_) => false,
+        };
+        if !replaced {
+            return Ok(None);
+        }
+
+        // The old file has been read to the end, so a line it left
+        // unfinished is passed on as it is.
+        if !self.pending.is_empty() {
+            let unfinished = std::mem::take(&mut self.pending);
+            self.emit(&unfinished, unfinished.len(), each)?;
+        }
+
+        self.file = File::open(&self.path)?;
+        self.handle = Handle::from_file(self.file.try_clone()?)?;
+        self.restart();
+        self.read_available(each)?;
+        Ok(Some(Change::Replaced))
+    }
+
+    fn restart(&mut self) {
+        self.position = 0;
+        self.pending.clear();
+        self.line_number = 0;
+    }
+
+    /// Reads up to the current end of the file, passing on each line as soon
+    /// as it is complete.
+    fn read_available(&mut self, each: &mut impl FnMut(Match) -> io::Result<()>) -> io::Result<()> {
+        let mut chunk = vec![0; CHUNK_LEN];
+
+        loop {
+            let read = self.file.read(&mut chunk)?;
+            if read == 0 {
+                return Ok(());
+            }
+            let mut read = &chunk[..read];
+            if self.position == 0 && self.pending.is_empty() {
+                let text = text_start(read)?;
+                self.position = (read.len() - text.len()) as u64;
+                read = text;
+            }
+            self.pending.extend_from_slice(read);
+
+            let pending = std::mem::take(&mut self.pending);
+            let mut start = 0;
+            while let Some(newline) = memchr(b'\n', &pending[start..]) {
+                let end = start + newline + 1;
+                self.emit(&pending[start..end - 1], end - start, each)?;
+                start = end;
+            }
+            self.pending = pending;
+            self.pending.drain(..start);
+        }
+    }
+
+    /// Passes on `line`, which took up `len` bytes of the file including its
+    /// line terminator.
+    fn emit(
+        &mut self,
+        line: &[u8],
+        len: usize,
+        each: &mut impl FnMut(Match) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let offset = self.position as usize;
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        self.line_number += 1;
+        self.position += len as u64;
+
+        each(Match {
+            line_number: self.line_number,
+            byte_range: offset..offset + line.len(),
+            text: String::from_utf8_lossy(line),
+            submatches: Vec::new(),
+        })
+    }
+}
+
+/// Returns the start of a file without any UTF-8 BOM, or an error if it isn't
+/// UTF-8 text.
+fn text_start(start: &[u8]) -> io::Result<&[u8]> {
+    let refuse = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+    // UTF-16 is full of zero bytes, so it has to be recognised before it's
+    // taken for binary.
+    let text = encoding::as_utf8(start, None)
+        .ok_or_else(|| refuse("not UTF-8, so it can't be followed"))?;
+    if walk::is_binary(text) {
+        return Err(refuse("binary file, so it can't be followed"));
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+
+    fn poll(follower: &mut Follower) -> (Vec<(usize, String)>, Option<Change>) {
+        let mut lines = Vec::new();
+        let change = follower
+            .poll(&mut |line| {
+                lines.push((line.line_number, line.text.into_owned()));
+                Ok(())
+            })
+            .unwrap();
+        (lines, change)
+    }
+
+    fn append(path: &Path, contents: &str) {
+        let mut file = OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+    }
+
+    fn lines(lines: &[(usize, &str)]) -> Vec<(usize, String)> {
+        lines
+            .iter()
+            .map(|&(number, text)| (number, text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn follows_appends_truncation_and_replacement() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "started\r\nlistening\n").unwrap();
+
+        let mut follower = Follower::open(&path).unwrap();
+        assert_eq!(
+            (lines(&[(1, "started"), (2, "listening")]), None),
+            poll(&mut follower)
+        );
+        assert_eq!((lines(&[]), None), poll(&mut follower));
+
+        // A line is only passed on once it's finished.
+        append(&path, "request ");
+        assert_eq!((lines(&[]), None), poll(&mut follower));
+        append(&path, "one\nrequest two\n");
+        assert_eq!(
+            (lines(&[(3, "request one"), (4, "request two")]), None),
+            poll(&mut follower)
+        );
+
+        fs::write(&path, "cleared\n").unwrap();
+        assert_eq!(
+            (lines(&[(1, "cleared")]), Some(Change::Truncated)),
+            poll(&mut follower)
+        );
+
+        // Rotation: the old file gets a last line, possibly unfinished, before
+        // a new file takes its place.
+        append(&path, "stopping");
+        fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+        assert_eq!((lines(&[]), None), poll(&mut follower));
+        fs::write(&path, "rotated\n").unwrap();
+        assert_eq!(
+            (
+                lines(&[(2, "stopping"), (1, "rotated")]),
+                Some(Change::Replaced)
+            ),
+            poll(&mut follower)
+        );
+    }
+
+    #[test]
+    fn follows_only_utf8_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+
+        // A UTF-8 BOM is skipped, but still counts towards byte offsets.
+        fs::write(&path, "\u{FEFF}started\n").unwrap();
+        let mut follower = Follower::open(&path).unwrap();
+        let mut found = Vec::new();
+        follower
+            .poll(&mut |line| {
+                found.push((line.byte_range, line.text.into_owned()));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(vec![(3..10, String::from("started"))], found);
+
+        let refused = |contents: &[u8]| {
+            fs::write(&path, contents).unwrap();
+            let mut follower = Follower::open(&path).unwrap();
+            let err = follower.poll(&mut |_| Ok(())).unwrap_err();
+            err.to_string()
+        };
+        assert_eq!(
+            "binary file, so it can't be followed",
+            refused(b"\x7fELF\x02\x01\x00\x00")
+        );
+        assert_eq!(
+            "not UTF-8, so it can't be followed",
+            refused(b"\xff\xfes\x00t\x00\n\x00")
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Approximate matching, for `--fuzzy`.
+//!
+//! A line matches if some part of it is within a given Levenshtein distance
+//! of the query: it can be turned into the query by inserting, deleting or
+//! substituting at most that many characters. This uses Sellers' variant of
+//! the usual dynamic programming algorithm, in which a match may start
+//! anywhere in the line, and takes time proportional to the length of the
+//! line times the length of the query.
+
+use std::ops::Range;
+
+use crate::fold;
+
+/// Finds the part of `line` closest to `query`, and returns its distance
+/// from the query along with its byte range, if the distance is at most
+/// `max_distance`. Of equally close parts, the one that ends first wins.
+pub fn find(
+    query: &[char],
+    line: &str,
+    max_distance: usize,
+    ignore_case: bool,
+) -> Option<(usize, Range<usize>)> {
+    // For each prefix of the query, the distance of the closest part of the
+    // line that ends at the current character, and where that part starts.
+    let mut previous: Vec<(usize, usize)> = (0..=query.len()).map(|i| (i, 0)).collect();
+    let mut current = previous.clone();
+    let mut best = (query.len(), 0..0);
+
+    for (offset, c) in line.char_indices() {
+        let end = offset + c.len_utf8();
+        // A match may start at any character, at no cost.
+        current[0] = (0, end);
+
+        for (i, expected) in query.iter().enumerate() {
+            let (diagonal, diagonal_start) = previous[i];
+            let substitute = diagonal + usize::from(!same(*expected, c, ignore_case));
+            let skip_query = current[i].0 + 1;
+            let skip_line = previous[i + 1].0 + 1;
+
+            current[i + 1] = if substitute <= skip_query && substitute <= skip_line {
+                (substitute, diagonal_start)
+            } else if skip_line <= skip_query {
+                (skip_line, previous[i + 1].1)
+            } else {
+                (skip_query, current[i].1)
+            };
+        }
+
+        let (distance, start) = current[query.len()];
+        if distance < best.0 {
+            best = (distance, start..end);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    (best.0 <= max_distance).then_some(best)
+}
+
+fn same(expected: char, c: char, ignore_case: bool) -> bool {
+    expected == c || (ignore_case && fold::fold_char(expected).eq(fold::fold_char(c)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_str(query: &str, line: &str, max_distance: usize) -> Option<(usize, Range<usize>)> {
+        let query: Vec<char> = query.chars().collect();
+        find(&query, line, max_distance, false)
+    }
+
+    #[test]
+    fn finds_closest_substring() {
+        assert_eq!(Some((0, 7..11)), find_str("frog", "like a frog", 1));
+        assert_eq!(Some((1, 7..10)), find_str("frog", "like a fog", 1));
+        assert_eq!(Some((1, 2..6)), find_str("frog", "a fr0g", 2));
+        assert_eq!(None, find_str("frog", "a toad", 2));
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        assert_eq!(Some((1, 0..6)), find_str("naive", "naïve", 1));
+    }
+
+    #[test]
+    fn ignoring_case() {
+        let query: Vec<char> = "FROG".chars().collect();
+        assert_eq!(Some((0, 2..6)), find(&query, "a frog", 0, true));
+        assert_eq!(None, find(&query, "a frog", 0, false));
+    }
+}
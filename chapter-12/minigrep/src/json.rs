@@ -0,0 +1,109 @@
+//! The records written by `--json`, one JSON object per line, modelled on
+//! ripgrep's JSON Lines format.
+//!
+//! Each file with matches produces a `begin` record, then a `match` or
+//! `context` record per printed line, then an `end` record with statistics.
+//! A single `summary` record follows the last file.
+
+use std::ops::Range;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+/// Counts kept for the `end` and `summary` records.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub elapsed: Duration,
+    pub searches: usize,
+    pub searches_with_match: usize,
+    pub matched_lines: usize,
+    pub matches: usize,
+}
+
+impl Stats {
+    pub fn add(&mut self, other: &Stats) {
+        self.elapsed += other.elapsed;
+        self.searches += other.searches;
+        self.searches_with_match += other.searches_with_match;
+        self.matched_lines += other.matched_lines;
+        self.matches += other.matches;
+    }
+
+    fn to_json(self) -> Value {
+        json!({
+            "elapsed": duration(self.elapsed),
+            "searches": self.searches,
+            "searches_with_match": self.searches_with_match,
+            "matched_lines": self.matched_lines,
+            "matches": self.matches,
+        })
+    }
+}
+
+pub fn begin(path: &str) -> Value {
+    json!({
+        "type": "begin",
+        "data": { "path": { "text": path } },
+    })
+}
+
+/// A `match` record for a selected line, or a `context` record for a line
+/// printed only as context.
+pub fn line(
+    path: &str,
+    selected: bool,
+    line_number: usize,
+    byte_offset: usize,
+    text: &str,
+    submatches: &[Range<usize>],
+) -> Value {
+    let submatches: Vec<Value> = submatches
+        .iter()
+        .map(|submatch| {
+            json!({
+                "match": { "text": &text[submatch.clone()] },
+                "start": submatch.start,
+                "end": submatch.end,
+            })
+        })
+        .collect();
+
+    json!({
+        "type": if selected { "match" } else { "context" },
+        "data": {
+            "path": { "text": path },
+            "lines": { "text": text },
+            "line_number": line_number,
+            "absolute_offset": byte_offset,
+            "submatches": submatches,
+        },
+    })
+}
+
+pub fn end(path: &str, stats: Stats) -> Value {
+    json!({
+        "type": "end",
+        "data": {
+            "path": { "text": path },
+            "stats": stats.to_json(),
+        },
+    })
+}
+
+pub fn summary(elapsed_total: Duration, stats: Stats) -> Value {
+    json!({
+        "type": "summary",
+        "data": {
+            "elapsed_total": duration(elapsed_total),
+            "stats": stats.to_json(),
+        },
+    })
+}
+
+fn duration(duration: Duration) -> Value {
+    json!({
+        "secs": duration.as_secs(),
+        "nanos": duration.subsec_nanos(),
+        "human": format!("{:.6}s", duration.as_secs_f64()),
+    })
+}
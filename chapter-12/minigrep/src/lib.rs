@@ -1,71 +1,827 @@
+use std::borrow::Cow;
 use std::error::Error;
-use std::{env, fs};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Instant;
 
-pub struct Config<'a> {
-    pub query: &'a String,
-    pub file_path: &'a String,
-    pub ignore_case: bool,
+use memchr::{memchr_iter, memrchr};
+use memmap2::Mmap;
+use regex::Regex;
+
+mod browser;
+mod config;
+mod decompress;
+mod edit;
+mod encoding;
+mod fields;
+mod fold;
+mod follow;
+mod fuzzy;
+mod json;
+mod literal;
+mod matcher;
+mod pool;
+mod printer;
+mod searcher;
+mod tui;
+mod walk;
+
+use crate::browser::Browser;
+pub use crate::config::{ColorChoice, Config, ConfigBuilder, ConfigError, USAGE, VERSION};
+pub use crate::encoding::Encoding;
+use crate::follow::{Change, Follower};
+use crate::matcher::{MatchMode, Matcher, Pattern};
+use crate::printer::Printer;
+pub use crate::searcher::{Matches, Searcher};
+use crate::walk::WalkError;
+
+/// A line found by a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'a> {
+    /// The line's number, counting from 1.
+    pub line_number: usize,
+    /// Where the line sits in the searched contents, not including its line
+    /// terminator.
+    pub byte_range: Range<usize>,
+    pub text: Cow<'a, str>,
+    /// Where each match sits within `text`, from left to right. Empty for
+    /// lines that are only context.
+    pub submatches: Vec<Range<usize>>,
+}
+
+impl Match<'_> {
+    /// Copies the line's text if it is borrowed, so the match can outlive the
+    /// contents it was found in.
+    pub fn into_owned(self) -> Match<'static> {
+        Match {
+            line_number: self.line_number,
+            byte_range: self.byte_range,
+            text: Cow::Owned(self.text.into_owned()),
+            submatches: self.submatches,
+        }
+    }
 }
 
-impl<'a> Config<'a> {
-    pub fn build(args: &'a [String]) -> Result<Config<'a>, &'static str> {
-        if args.len() < 3 {
-            return Err("not enough arguments");
+/// Searches every path in `config`, printing results to stdout.
+///
+/// Returns whether any line was selected, which is what decides grep's exit
+/// status. A file that can't be searched is reported on stderr without
+/// stopping the search, but makes `run` return an error at the end.
+pub fn run(config: Config) -> Result<bool, Box<dyn Error>> {
+    let searcher = Searcher::new(&config)?;
+
+    let file_paths = if config.file_paths.is_empty() {
+        vec![PathBuf::from(STDIN_PATH)]
+    } else {
+        config.file_paths.clone()
+    };
+
+    // Like grep, only prefix lines with their path when more than one file
+    // could be searched.
+    let show_paths = file_paths.len() > 1 || file_paths.iter().any(|path| path.is_dir());
+
+    let started = Instant::now();
+    let files = walk::files(&file_paths, &config)?;
+
+    if config.interactive {
+        return browse(&config, &searcher, files);
+    }
+
+    let stdout = io::stdout();
+    let color = config.color.enabled(&stdout);
+    let mut printer = Printer::new(&config, color, stdout.lock());
+    let mut summary = Summary::default();
+
+    if config.follow {
+        let [path] = file_paths.as_slice() else {
+            return Err("--follow needs exactly one file".into());
+        };
+        if path == Path::new(STDIN_PATH) || path.is_dir() {
+            return Err("--follow needs a file, not standard input or a directory".into());
         }
+        let count = follow(&config, &searcher, &mut printer, path)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        return Ok(count > 0);
+    }
 
-        let query = &args[1];
-        let file_path = &args[2];
+    if config.threads == 1 || files.len() == 1 {
+        // Print straight to stdout, so results appear as soon as they're
+        // found. That matters when searching a pipe that stays open.
+        for file in files {
+            summary.add(search_file(
+                &config,
+                &searcher,
+                &mut printer,
+                file,
+                show_paths,
+            ));
 
-        let ignore_case = env::var("IGNORE_CASE").is_ok();
+            // Once anything has matched, a quiet search already knows its
+            // answer.
+            if config.quiet && summary.selected_any {
+                break;
+            }
+        }
+    } else {
+        pool::for_each_ordered(
+            config.threads,
+            files,
+            |file| {
+                let mut file_printer = Printer::new(&config, color, Vec::new());
+                let searched = search_file(&config, &searcher, &mut file_printer, file, show_paths);
+                (file_printer, searched)
+            },
+            |(file_printer, searched)| {
+                printer.append(file_printer)?;
+                summary.add(searched);
+                Ok::<_, io::Error>(!(config.quiet && summary.selected_any))
+            },
+        )?;
+    }
 
-        Ok(Config { query, file_path, ignore_case })
+    if config.quiet && summary.selected_any {
+        return Ok(true);
+    }
+    if !config.quiet {
+        printer.finish(started.elapsed())?;
     }
+    if summary.failures > 0 {
+        let failures = summary.failures;
+        return Err(format!("{failures} file(s) could not be searched").into());
+    }
+
+    Ok(summary.selected_any)
+}
+
+/// What `run` has learned so far from the files it searched.
+#[derive(Default)]
+struct Summary {
+    selected_any: bool,
+    failures: usize,
 }
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(config.file_path)?;
+impl Summary {
+    fn add(&mut self, searched: Result<usize, String>) {
+        match searched {
+            Ok(count) => self.selected_any |= count > 0,
+            Err(err) => {
+                eprintln!("minigrep: {err}");
+                self.failures += 1;
+            }
+        }
+    }
+}
 
-    let results = if config.ignore_case {
-        search_case_insensitive(&config.query, &contents)
+/// Searches `files`, then lets the user browse what was found, printing the
+/// `path:line` they pick.
+fn browse(
+    config: &Config,
+    searcher: &Searcher,
+    files: Vec<Result<PathBuf, WalkError>>,
+) -> Result<bool, Box<dyn Error>> {
+    if !io::stderr().is_terminal() {
+        return Err("--interactive needs a terminal to draw on".into());
+    }
+
+    let mut found = Vec::new();
+    let mut failures = 0;
+    for file in files {
+        let path = match file {
+            Ok(path) => path,
+            Err(err) => {
+                eprintln!("minigrep: {err}");
+                failures += 1;
+                continue;
+            }
+        };
+        match collect_hits(config, searcher, &path) {
+            Ok(Some(file)) => found.push(file),
+            Ok(None) => {}
+            Err(err) => {
+                eprintln!("minigrep: {}: {err}", path.display());
+                failures += 1;
+            }
+        }
+    }
+
+    if found.is_empty() {
+        if failures > 0 {
+            return Err(format!("{failures} file(s) could not be searched").into());
+        }
+        return Ok(false);
+    }
+
+    if let Some(location) = tui::run(Browser::new(found))? {
+        println!("{location}");
+    }
+    Ok(true)
+}
+
+/// Reads the whole of the file at `path` for the browser, along with which of
+/// its lines are selected. Returns `None` if none are.
+fn collect_hits(
+    config: &Config,
+    searcher: &Searcher,
+    path: &Path,
+) -> Result<Option<browser::File>, Box<dyn Error>> {
+    let decompressed = if config.search_zip {
+        decompress::open(path)?
     } else {
-        search(&config.query, &contents)
+        None
+    };
+    let (reader, name): (Box<dyn BufRead>, &Path) = if let Some(decompressed) = decompressed {
+        (Box::new(BufReader::new(decompressed)), path)
+    } else if path == Path::new(STDIN_PATH) {
+        (Box::new(io::stdin().lock()), Path::new("(standard input)"))
+    } else {
+        (Box::new(BufReader::new(File::open(path)?)), path)
     };
 
-    for line in results {
-        println!("{line}");
+    let mut reader = encoding::decode(reader, config.encoding)?;
+    if walk::is_binary(reader.fill_buf()?) {
+        return Ok(None);
     }
+    let mut contents = Vec::new();
+    reader.read_to_end(&mut contents)?;
+    let contents = String::from_utf8_lossy(&contents);
 
-    Ok(())
+    let mut lines = Vec::new();
+    let mut hits = Vec::new();
+    for (line, selected) in searcher.lines(&contents) {
+        if line.line_number == 1 {
+            searcher.check_header(&line.text)?;
+        }
+        if selected {
+            hits.push(browser::Hit {
+                index: lines.len(),
+                submatches: line.submatches,
+            });
+        }
+        lines.push(line.text.into_owned());
+    }
+
+    if hits.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(browser::File {
+        path: name.to_path_buf(),
+        lines,
+        hits,
+    }))
+}
+
+/// Searches one of the files found by `walk::files`, turning any error into a
+/// message that names the file.
+fn search_file(
+    config: &Config,
+    searcher: &Searcher,
+    printer: &mut Printer<impl Write>,
+    file: Result<PathBuf, WalkError>,
+    show_path: bool,
+) -> Result<usize, String> {
+    let path = file.map_err(|err| err.to_string())?;
+
+    search_path(config, searcher, printer, &path, show_path)
+        .map_err(|err| format!("{}: {err}", path.display()))
 }
 
-pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    let mut results = Vec::new();
+/// The path that stands for standard input.
+pub(crate) const STDIN_PATH: &str = "-";
 
-    for line in contents.lines() {
-        if line.contains(query) {
-            results.push(line);
+fn search_path(
+    config: &Config,
+    searcher: &Searcher,
+    printer: &mut Printer<impl Write>,
+    path: &Path,
+    show_path: bool,
+) -> Result<usize, Box<dyn Error>> {
+    if config.in_place {
+        if path == Path::new(STDIN_PATH) {
+            return Err("standard input can't be edited in place".into());
         }
+        return edit::in_place(searcher, path);
     }
 
-    results
+    if config.search_zip {
+        if let Some(decompressed) = decompress::open(path)? {
+            let reader = BufReader::new(decompressed);
+            return search_reader(config, searcher, printer, reader, path, show_path);
+        }
+    }
+
+    if path == Path::new(STDIN_PATH) {
+        let stdin = io::stdin().lock();
+        let name = Path::new("(standard input)");
+        search_reader(config, searcher, printer, stdin, name, show_path)
+    } else {
+        let file = File::open(path)?;
+        if config.mmap {
+            if let Some(map) = map_file(&file) {
+                return search_mapped(config, searcher, printer, &map, path, show_path);
+            }
+        }
+        search_reader(
+            config,
+            searcher,
+            printer,
+            BufReader::new(file),
+            path,
+            show_path,
+        )
+    }
 }
 
-pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    let query = query.to_lowercase();
-    let mut results = Vec::new();
+/// Files smaller than this are read normally even with `--mmap`, since
+/// mapping them costs more than copying them does.
+const MMAP_MIN_LEN: u64 = 1 << 20;
 
-    for line in contents.lines() {
-        if line.to_lowercase().contains(&query) {
-            results.push(line);
+/// Maps `file` into memory if it is a large regular file, so that it can be
+/// searched without copying it into a buffer first. Returns `None` for pipes
+/// and other special files, small files, or if mapping fails, in which case
+/// the file should be read normally.
+fn map_file(file: &File) -> Option<Mmap> {
+    let metadata = file.metadata().ok()?;
+    if !metadata.is_file() || metadata.len() < MMAP_MIN_LEN {
+        return None;
+    }
+
+    // SAFETY: the map is only read from, and is dropped once the file has
+    // been searched. If another process truncates the file in the meantime,
+    // reading the pages that are gone kills minigrep with SIGBUS, which is
+    // why mapping is only done when asked for with `--mmap`.
+    unsafe { Mmap::map(file) }.ok()
+}
+
+/// Searches the file at `path`, then goes on searching the lines appended to
+/// it until minigrep is interrupted. Only a search that stops at its first
+/// match, such as with `-q` or `-l`, returns by itself.
+fn follow(
+    config: &Config,
+    searcher: &Searcher,
+    printer: &mut Printer<impl Write>,
+    path: &Path,
+) -> Result<usize, Box<dyn Error>> {
+    let mut follower = Follower::open(path)?;
+    let mut selector = searcher.selector();
+    let stop_at_first = config.files_with_matches || config.quiet;
+    let mut count = 0;
+
+    printer.begin(path, false);
+
+    loop {
+        let change = follower.poll(&mut |mut line| {
+            if stop_at_first && count > 0 {
+                return Ok(());
+            }
+
+            let selected = selector.select(&mut line);
+            if line.line_number == 1 {
+                selector.check_header()?;
+            }
+            if selected {
+                count += 1;
+            }
+            if stop_at_first {
+                return Ok(());
+            }
+
+            let line = if selected {
+                searcher.replace(line)
+            } else {
+                line
+            };
+            printer.line(&selector.project(line), selected)
+        })?;
+
+        match change {
+            Some(Change::Truncated) => eprintln!("minigrep: {}: file truncated", path.display()),
+            Some(Change::Replaced) => eprintln!(
+                "minigrep: {}: file replaced; following the new file",
+                path.display()
+            ),
+            None => {}
+        }
+
+        if stop_at_first && count > 0 {
+            if config.files_with_matches {
+                printer.path(path)?;
+            }
+            printer.end()?;
+            return Ok(count);
+        }
+
+        thread::sleep(follow::POLL_INTERVAL);
+    }
+}
+
+/// Searches `reader` a line at a time, so that only the current line (plus
+/// any before-context) is ever held in memory. Returns how many lines were
+/// selected, although searches that only need to know whether there was a
+/// match stop counting at the first one.
+///
+/// With `--multiline`, matches can span lines, so the whole of `reader` is
+/// read into memory and searched at once instead.
+///
+/// Text in other encodings is transcoded to UTF-8 first, and byte offsets are
+/// then positions in the transcoded text.
+fn search_reader(
+    config: &Config,
+    searcher: &Searcher,
+    printer: &mut Printer<impl Write>,
+    reader: impl BufRead,
+    path: &Path,
+    show_path: bool,
+) -> Result<usize, Box<dyn Error>> {
+    let mut reader = encoding::decode(reader, config.encoding)?;
+    if walk::is_binary(reader.fill_buf()?) {
+        return Ok(0);
+    }
+
+    if config.multiline {
+        let mut contents = Vec::new();
+        reader.read_to_end(&mut contents)?;
+        return search_contents(config, searcher, printer, &contents, path, show_path);
+    }
+
+    if let Some(query) = literal_query(config, searcher) {
+        let query = literal::Query::new(query);
+        return report(config, searcher, printer, path, show_path, |handle| {
+            search_chunks(reader, &query, handle)
+        });
+    }
+
+    report(config, searcher, printer, path, show_path, |handle| {
+        let mut lines = LineReader::new(reader);
+        let mut selector = searcher.selector();
+
+        while let Some(mut line) = lines.next_line()? {
+            let selected = selector.select(&mut line);
+            if line.line_number == 1 {
+                selector.check_header()?;
+            }
+            if handle(selector.project(line), selected)? {
+                break;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// How much of a reader `search_chunks` reads at once.
+const CHUNK_LEN: usize = 256 * 1024;
+
+/// Searches `reader` for `query` a chunk at a time, rather than a line at a
+/// time, passing each line that contains it to `handle`. Only the current
+/// chunk, and a line that runs past its end, are held in memory.
+fn search_chunks(
+    mut reader: impl BufRead,
+    query: &literal::Query,
+    handle: &mut LineHandler,
+) -> Result<(), Box<dyn Error>> {
+    let mut buf = Vec::with_capacity(CHUNK_LEN);
+    let mut line_number = 1;
+    let mut offset = 0;
+
+    loop {
+        let read = (&mut reader).take(CHUNK_LEN as u64).read_to_end(&mut buf)?;
+        let at_end = read == 0;
+        // A line that carries on into the next chunk is searched with it.
+        let complete = match memrchr(b'\n', &buf) {
+            _ if at_end => buf.len(),
+            Some(newline) => newline + 1,
+            None => continue,
+        };
+
+        for line in query.lines(&buf[..complete], line_number, offset) {
+            if handle(line, true)? {
+                return Ok(());
+            }
         }
+        if at_end {
+            return Ok(());
+        }
+
+        line_number += memchr_iter(b'\n', &buf[..complete]).count();
+        offset += complete;
+        buf.drain(..complete);
+    }
+}
+
+/// Searches a mapped file where it lies in memory, rather than copying it
+/// out a line at a time. Only UTF-8 can be searched in place, so a file that
+/// needs transcoding is read like any other.
+fn search_mapped(
+    config: &Config,
+    searcher: &Searcher,
+    printer: &mut Printer<impl Write>,
+    map: &[u8],
+    path: &Path,
+    show_path: bool,
+) -> Result<usize, Box<dyn Error>> {
+    let Some(contents) = encoding::as_utf8(map, config.encoding) else {
+        return search_reader(config, searcher, printer, map, path, show_path);
+    };
+    if walk::is_binary(contents) {
+        return Ok(0);
     }
 
-    results
+    search_contents(config, searcher, printer, contents, path, show_path)
+}
+
+/// Searches the whole of a file that is already in memory. Byte offsets are
+/// positions in `contents`, even where it isn't valid UTF-8, except with
+/// `--multiline`.
+fn search_contents(
+    config: &Config,
+    searcher: &Searcher,
+    printer: &mut Printer<impl Write>,
+    contents: &[u8],
+    path: &Path,
+    show_path: bool,
+) -> Result<usize, Box<dyn Error>> {
+    let query = literal_query(config, searcher).map(literal::Query::new);
+
+    report(config, searcher, printer, path, show_path, |handle| {
+        if let Some(query) = query {
+            for line in query.lines(contents, 1, 0) {
+                if handle(line, true)? {
+                    break;
+                }
+            }
+            return Ok(());
+        }
+
+        if config.multiline {
+            // Matches can span lines, so the patterns have to be matched
+            // against the whole of the text, which is only copied if it
+            // isn't valid UTF-8.
+            let contents = String::from_utf8_lossy(contents);
+            for (line, selected) in searcher.lines(&contents) {
+                if handle(line, selected)? {
+                    break;
+                }
+            }
+            return Ok(());
+        }
+
+        let mut selector = searcher.selector();
+        for mut line in byte_lines(contents) {
+            let selected = selector.select(&mut line);
+            if line.line_number == 1 {
+                selector.check_header()?;
+            }
+            if handle(selector.project(line), selected)? {
+                break;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Returns the query to look for with `literal::Query` rather than a line at
+/// a time, if there is one. That's only the case for a single literal query,
+/// as the most common search, and only without context, since lines that
+/// aren't selected are only printed as context.
+///
+/// Scanning a whole buffer for the query is several times faster than going
+/// line by line when few lines match, and still a little faster when every
+/// line does, since each matching line costs the same either way: finding its
+/// ends, decoding it and recording where the query is in it. `cargo bench`
+/// compares the two.
+fn literal_query<'s>(config: &Config, searcher: &'s Searcher) -> Option<&'s str> {
+    let needs_context = config.before_context > 0 || config.after_context > 0;
+    searcher.whole_buffer_query().filter(|_| !needs_context)
+}
+
+/// A function that is given each line of a file and whether it is selected,
+/// and returns whether the rest of the file can be skipped.
+type LineHandler<'h> = dyn FnMut(Match, bool) -> io::Result<bool> + 'h;
+
+/// Prints the lines of the file at `path` that `search` passes to its
+/// handler, or whatever else the config asks to be printed about the file,
+/// and returns how many lines were selected.
+fn report(
+    config: &Config,
+    searcher: &Searcher,
+    printer: &mut Printer<impl Write>,
+    path: &Path,
+    show_path: bool,
+    search: impl FnOnce(&mut LineHandler) -> Result<(), Box<dyn Error>>,
+) -> Result<usize, Box<dyn Error>> {
+    let lists_files = config.files_with_matches || config.files_without_match;
+    let stop_at_first = lists_files || config.quiet;
+    let prints_lines = !(stop_at_first || config.count);
+
+    let mut count = 0;
+
+    printer.begin(path, show_path);
+
+    search(&mut |line: Match, selected: bool| -> io::Result<bool> {
+        if selected {
+            count += 1;
+
+            if stop_at_first {
+                return Ok(true);
+            }
+        }
+
+        if prints_lines {
+            let line = if selected {
+                searcher.replace(line)
+            } else {
+                line
+            };
+            printer.line(&line, selected)?;
+        }
+
+        Ok(false)
+    })?;
+
+    if config.quiet {
+        // Only the exit status is wanted.
+    } else if lists_files {
+        if (count > 0) == config.files_with_matches {
+            printer.path(path)?;
+        }
+    } else if config.count {
+        printer.count(count)?;
+    }
+
+    printer.end()?;
+
+    Ok(count)
+}
+
+/// Splits `contents` into lines the same way `str::lines` does, but keeps
+/// track of each line's number and position.
+fn lines(contents: &str) -> impl Iterator<Item = Match<'_>> {
+    let mut start = 0;
+
+    contents
+        .split_inclusive('\n')
+        .enumerate()
+        .map(move |(index, line)| {
+            let text = line.strip_suffix('\n').unwrap_or(line);
+            let text = text.strip_suffix('\r').unwrap_or(text);
+            let byte_range = start..start + text.len();
+            start += line.len();
+
+            Match {
+                line_number: index + 1,
+                byte_range,
+                text: Cow::Borrowed(text),
+                submatches: Vec::new(),
+            }
+        })
+}
+
+/// Like `lines`, but for contents that may not be valid UTF-8, which are
+/// decoded lossily a line at a time. Byte ranges are positions in `contents`.
+fn byte_lines(contents: &[u8]) -> impl Iterator<Item = Match<'_>> {
+    let mut start = 0;
+
+    contents
+        .split_inclusive(|&byte| byte == b'\n')
+        .enumerate()
+        .map(move |(index, line)| {
+            let text = line.strip_suffix(b"\n").unwrap_or(line);
+            let text = text.strip_suffix(b"\r").unwrap_or(text);
+            let byte_range = start..start + text.len();
+            start += line.len();
+
+            Match {
+                line_number: index + 1,
+                byte_range,
+                text: String::from_utf8_lossy(text),
+                submatches: Vec::new(),
+            }
+        })
+}
+
+pub fn search<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
+    // A query that is empty or spans lines can't be found by scanning the
+    // whole buffer, but then it's hardly worth speeding up anyway.
+    if query.is_empty() || query.contains(['\n', '\r']) {
+        literal_matcher(query, MatchMode::Substring).search(contents)
+    } else {
+        literal::Query::new(query)
+            .lines(contents.as_bytes(), 1, 0)
+            .collect()
+    }
+}
+
+pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
+    Matcher::new(
+        Pattern::CaseInsensitive(vec![fold::fold(query)]),
+        MatchMode::Substring,
+    )
+    .search(contents)
+}
+
+/// Like `search`, but `pattern` is a compiled regular expression rather than
+/// a literal, so the query can use character classes, anchors, alternation
+/// and repetition.
+pub fn search_regex<'a>(pattern: &Regex, contents: &'a str) -> Vec<Match<'a>> {
+    Matcher::new(Pattern::Regex(pattern.clone()), MatchMode::Substring).search(contents)
+}
+
+/// Like `search`, but tolerates typos: a line matches if some part of it is
+/// within `max_distance` insertions, deletions or substitutions of `query`.
+/// Each line found comes with the distance of its closest part, which is its
+/// only submatch.
+pub fn search_fuzzy<'a>(
+    query: &str,
+    contents: &'a str,
+    max_distance: usize,
+) -> Vec<(Match<'a>, usize)> {
+    let query: Vec<char> = query.chars().collect();
+
+    lines(contents)
+        .filter_map(|mut line| {
+            let (distance, found) = fuzzy::find(&query, &line.text, max_distance, false)?;
+            line.submatches = vec![found];
+            Some((line, distance))
+        })
+        .collect()
+}
+
+/// Like `search`, but only matches `query` as a whole word, so "to" matches
+/// "go to" but not "Toad" or "tomorrow". Any letter or digit, in any script,
+/// counts as part of a word, as does `_`.
+pub fn search_words<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
+    literal_matcher(query, MatchMode::Word).search(contents)
+}
+
+/// Like `search`, but only matches lines that are exactly `query`.
+pub fn search_whole_lines<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
+    literal_matcher(query, MatchMode::Line).search(contents)
+}
+
+fn literal_matcher(query: &str, mode: MatchMode) -> Matcher {
+    let pattern = Pattern::literals(&[query]).expect("a single query fits in the automaton");
+    Matcher::new(pattern, mode)
+}
+
+/// Reads lines from a `BufRead` one at a time, reusing a single buffer.
+///
+/// Lines that are not valid UTF-8 are decoded lossily rather than stopping
+/// the search. Byte ranges are positions in the raw input.
+struct LineReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    text: String,
+    line_number: usize,
+    offset: usize,
+}
+
+impl<R: BufRead> LineReader<R> {
+    fn new(reader: R) -> LineReader<R> {
+        LineReader {
+            reader,
+            buf: Vec::new(),
+            text: String::new(),
+            line_number: 0,
+            offset: 0,
+        }
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<Match<'_>>> {
+        self.buf.clear();
+        let read = self.reader.read_until(b'\n', &mut self.buf)?;
+        if read == 0 {
+            return Ok(None);
+        }
+
+        let line = self.buf.strip_suffix(b"\n").unwrap_or(&self.buf);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        self.text.clear();
+        self.text.push_str(&String::from_utf8_lossy(line));
+
+        self.line_number += 1;
+        let byte_range = self.offset..self.offset + line.len();
+        self.offset += read;
+
+        Ok(Some(Match {
+            line_number: self.line_number,
+            byte_range,
+            text: Cow::Borrowed(&self.text),
+            submatches: Vec::new(),
+        }))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
+
+    fn texts<'a>(matches: Vec<Match<'a>>) -> Vec<Cow<'a, str>> {
+        matches.into_iter().map(|m| m.text).collect()
+    }
 
     #[test]
     fn case_sensitive() {
@@ -76,7 +832,10 @@ safe, fast, productive.
 Pick three.
 Duct tape.";
 
-        assert_eq!(vec!["safe, fast, productive."], search(query, contents));
+        assert_eq!(
+            vec!["safe, fast, productive."],
+            texts(search(query, contents))
+        );
     }
 
     #[test]
@@ -90,7 +849,205 @@ Trust me.";
 
         assert_eq!(
             vec!["Rust:", "Trust me."],
-            search_case_insensitive(query, contents)
+            texts(search_case_insensitive(query, contents))
+        );
+    }
+
+    #[test]
+    fn regex() {
+        let pattern = Regex::new(r"^(Rust|Pick)\b|^[A-Z]\w+ [a-z]{2}\.$").unwrap();
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+
+        assert_eq!(
+            vec!["Rust:", "Pick three.", "Trust me."],
+            texts(search_regex(&pattern, contents))
+        );
+    }
+
+    #[test]
+    fn fuzzy() {
+        let query = "productive";
+        let contents = "\
+Rust:
+safe, fast, prodcutive.
+Pick three.
+Trust me, it's reproductive.";
+
+        let found: Vec<(usize, usize)> = search_fuzzy(query, contents, 2)
+            .into_iter()
+            .map(|(line, distance)| (line.line_number, distance))
+            .collect();
+        assert_eq!(vec![(2, 2), (4, 0)], found);
+    }
+
+    #[test]
+    fn whole_words_and_lines() {
+        let query = "to";
+        let contents = "\
+Toad
+tomorrow
+go to
+to";
+
+        assert_eq!(vec!["go to", "to"], texts(search_words(query, contents)));
+        assert_eq!(vec!["to"], texts(search_whole_lines(query, contents)));
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn match_positions() {
+        let query = "t";
+        let contents = "Rust:\r\nsafe, fast, productive.\n\nTrust me.";
+
+        assert_eq!(
+            vec![
+                Match {
+                    line_number: 1,
+                    byte_range: 0..5,
+                    text: Cow::Borrowed("Rust:"),
+                    submatches: vec![3..4],
+                },
+                Match {
+                    line_number: 2,
+                    byte_range: 7..30,
+                    text: Cow::Borrowed("safe, fast, productive."),
+                    submatches: vec![9..10, 18..19],
+                },
+                Match {
+                    line_number: 4,
+                    byte_range: 32..41,
+                    text: Cow::Borrowed("Trust me."),
+                    submatches: vec![4..5],
+                },
+            ],
+            search(query, contents)
+        );
+    }
+
+    #[test]
+    fn maps_only_large_regular_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut contents = "x".repeat(MMAP_MIN_LEN as usize);
+        contents.push_str("\nfrog\n");
+        fs::write(dir.join("large"), &contents).unwrap();
+        fs::write(dir.join("small"), "frog\n").unwrap();
+
+        let map = map_file(&File::open(dir.join("large")).unwrap()).unwrap();
+        let config = Config::builder().pattern("frog").build().unwrap();
+        let searcher = Searcher::new(&config).unwrap();
+        let mut printer = Printer::new(&config, false, Vec::new());
+        let search = |printer: &mut Printer<_>, map: &[u8]| {
+            search_mapped(&config, &searcher, printer, map, dir, false).unwrap()
+        };
+        assert_eq!(1, search(&mut printer, &map));
+        // Text that needs transcoding is still found.
+        let utf16: Vec<u8> = "\u{FEFF}a frog\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(1, search(&mut printer, &utf16));
+
+        assert!(map_file(&File::open(dir.join("small")).unwrap()).is_none());
+        assert!(map_file(&File::open(dir).unwrap()).is_none());
+    }
+
+    #[test]
+    fn quiet_mapped_search_stops_at_the_first_match() {
+        let contents = "frog\n".repeat(1000);
+        for config in [
+            Config::builder().pattern("frog").quiet(true),
+            Config::builder().pattern("fr.g").regex(true).quiet(true),
+        ] {
+            let config = config.build().unwrap();
+            let searcher = Searcher::new(&config).unwrap();
+            let mut printer = Printer::new(&config, false, Vec::new());
+            let path = Path::new("frogs");
+            let count = search_mapped(
+                &config,
+                &searcher,
+                &mut printer,
+                contents.as_bytes(),
+                path,
+                false,
+            );
+            assert_eq!(1, count.unwrap());
+        }
+    }
+
+    #[test]
+    fn mapped_lines_are_placed_by_their_raw_bytes() {
+        let contents = b"caf\xe9\r\nfrog \xff\xfe frog\nfrog";
+        let found: Vec<_> = byte_lines(contents)
+            .map(|line| (line.line_number, line.byte_range, line.text))
+            .collect();
+        assert_eq!(
+            vec![
+                (1, 0..4, Cow::from("caf\u{FFFD}")),
+                (2, 6..18, Cow::from("frog \u{FFFD}\u{FFFD} frog")),
+                (3, 19..23, Cow::from("frog")),
+            ],
+            found
         );
+
+        let found: Vec<_> = literal::Query::new("frog")
+            .lines(contents, 1, 0)
+            .map(|line| line.byte_range)
+            .collect();
+        assert_eq!(vec![6..18, 19..23], found);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn searches_chunks_across_their_ends() {
+        // Lines of every length up to past a chunk, so that some of them
+        // straddle the end of one.
+        let mut contents = String::new();
+        for len in (0..CHUNK_LEN + 100).step_by(997) {
+            contents.push_str(&"x".repeat(len));
+            contents.push_str("frog\r\n");
+        }
+        contents.push_str("a last frog");
+
+        let query = literal::Query::new("frog");
+        let mut found = Vec::new();
+        search_chunks(contents.as_bytes(), &query, &mut |line, _| {
+            found.push(line.into_owned());
+            Ok(false)
+        })
+        .unwrap();
+        assert_eq!(search("frog", &contents), found);
+
+        // Stops when asked to.
+        let mut found = 0;
+        search_chunks(contents.as_bytes(), &query, &mut |_, _| {
+            found += 1;
+            Ok(true)
+        })
+        .unwrap();
+        assert_eq!(1, found);
+    }
+
+    #[test]
+    fn line_reader_tolerates_invalid_utf8() {
+        let input: &[u8] = b"Rust:\r\nsafe, \xff fast\nTrust me.";
+        let mut lines = LineReader::new(input);
+
+        let mut read = Vec::new();
+        while let Some(line) = lines.next_line().unwrap() {
+            read.push((line.line_number, line.byte_range, line.text.to_string()));
+        }
+
+        assert_eq!(
+            vec![
+                (1, 0..5, String::from("Rust:")),
+                (2, 7..19, String::from("safe, \u{FFFD} fast")),
+                (3, 20..29, String::from("Trust me.")),
+            ],
+            read
+        );
+    }
+}
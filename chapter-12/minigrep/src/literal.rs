@@ -0,0 +1,158 @@
+//! Literal search over a whole buffer at once.
+//!
+//! Rather than splitting the contents into lines and searching each one, this
+//! runs a fast substring search (memchr's `memmem`, which uses SIMD where it
+//! can) over the entire buffer, and only looks for the line boundaries around
+//! each hit. Most lines in a large file never match, so most of the buffer is
+//! never looked at more than once.
+
+use std::borrow::Cow;
+
+use memchr::memmem::Finder;
+use memchr::{memchr, memchr_iter, memrchr};
+
+use crate::Match;
+
+/// A literal query, prepared for searching any number of buffers.
+pub struct Query {
+    finder: Finder<'static>,
+}
+
+impl Query {
+    /// Prepares to search for `query`, which must not be empty or contain a
+    /// line terminator, since it could then match across or between lines.
+    pub fn new(query: &str) -> Query {
+        debug_assert!(!query.is_empty() && !query.contains(['\n', '\r']));
+        Query {
+            finder: Finder::new(query).into_owned(),
+        }
+    }
+
+    /// Returns the lines of `contents` that contain the query, finding each
+    /// one only when it is asked for. `contents` starts at line `first_line`
+    /// and byte `offset` of the input, which is where the lines' numbers and
+    /// byte ranges count from.
+    ///
+    /// Byte ranges are positions in `contents` itself, even if a line isn't
+    /// valid UTF-8 and so has its text decoded lossily.
+    pub fn lines<'q, 'a>(
+        &'q self,
+        contents: &'a [u8],
+        first_line: usize,
+        offset: usize,
+    ) -> Lines<'q, 'a> {
+        Lines {
+            finder: &self.finder,
+            bytes: contents,
+            at: 0,
+            line_number: first_line,
+            offset,
+        }
+    }
+}
+
+/// The lines containing a query, returned by `Query::lines`.
+pub struct Lines<'q, 'a> {
+    finder: &'q Finder<'static>,
+    bytes: &'a [u8],
+    /// Where to search from next: the start of the line after the last one
+    /// returned.
+    at: usize,
+    /// The number of the line that starts at `at`. Line numbers are counted
+    /// lazily, only up to the start of each matching line.
+    line_number: usize,
+    offset: usize,
+}
+
+impl<'a> Iterator for Lines<'_, 'a> {
+    type Item = Match<'a>;
+
+    fn next(&mut self) -> Option<Match<'a>> {
+        let bytes = self.bytes;
+        let at = self.at;
+        if at >= bytes.len() {
+            return None;
+        }
+
+        let found = at + self.finder.find(&bytes[at..])?;
+        let start = memrchr(b'\n', &bytes[at..found]).map_or(at, |newline| at + newline + 1);
+        let end = memchr(b'\n', &bytes[found..]).map_or(bytes.len(), |newline| found + newline);
+
+        let line_number = self.line_number + memchr_iter(b'\n', &bytes[at..start]).count();
+        self.line_number = line_number + 1;
+        self.at = end + 1;
+
+        let line = &bytes[start..end];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let text = String::from_utf8_lossy(line);
+
+        // The first hit is already known, so only the rest of the line needs
+        // searching for more. A line that had to be decoded lossily is
+        // searched again, since its positions may have moved.
+        let len = self.finder.needle().len();
+        let mut submatches = Vec::new();
+        let mut offset = match text {
+            Cow::Borrowed(_) => Some(found - start),
+            Cow::Owned(_) => self.finder.find(text.as_bytes()),
+        };
+        while let Some(at) = offset {
+            submatches.push(at..at + len);
+            offset = self
+                .finder
+                .find(&text.as_bytes()[at + len..])
+                .map(|next| at + len + next);
+        }
+
+        Some(Match {
+            line_number,
+            byte_range: self.offset + start..self.offset + start + line.len(),
+            text,
+            submatches,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lines;
+
+    /// The straightforward line-at-a-time search, for comparison.
+    fn search_lines<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
+        lines(contents)
+            .filter_map(|mut line| {
+                line.submatches = line
+                    .text
+                    .match_indices(query)
+                    .map(|(start, found)| start..start + found.len())
+                    .collect();
+                (!line.submatches.is_empty()).then_some(line)
+            })
+            .collect()
+    }
+
+    fn search<'a>(query: &str, contents: &'a str) -> Vec<Match<'a>> {
+        Query::new(query).lines(contents.as_bytes(), 1, 0).collect()
+    }
+
+    #[test]
+    fn agrees_with_line_by_line_search() {
+        let contents = "\
+to be\r\nor not to be\n\nthat is\ntoto\n\n\nthe question: tö, to";
+
+        for query in ["to", "be", "tö", "question", "t", "missing", "toto"] {
+            assert_eq!(
+                search_lines(query, contents),
+                search(query, contents),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn matches_at_the_edges() {
+        assert_eq!(search_lines("a", "a"), search("a", "a"));
+        assert_eq!(search_lines("a", "\na\n"), search("a", "\na\n"));
+        assert_eq!(search_lines("a", "b\r\na\r\n"), search("a", "b\r\na\r\n"));
+    }
+}
@@ -0,0 +1,571 @@
+use std::error::Error;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use aho_corasick::{AhoCorasick, BuildError, Input, MatchKind};
+use regex::{Regex, RegexBuilder};
+
+use crate::{fold, fuzzy, lines, Config, Match};
+
+/// The patterns from a `Config`, prepared once so they can be reused for every
+/// line that is searched.
+pub struct Matcher {
+    pattern: Pattern,
+    mode: MatchMode,
+    /// The query, if there is only one and it is matched exactly as it is
+    /// written.
+    literal: Option<String>,
+}
+
+/// However many patterns there are, each line is scanned only once: a set of
+/// literals is compiled into a single Aho-Corasick automaton, and a set of
+/// regular expressions into a single alternation.
+pub enum Pattern {
+    Literal(AhoCorasick),
+    /// Holds the queries already case-folded.
+    CaseInsensitive(Vec<Vec<char>>),
+    Regex(Regex),
+    /// Matches the part of a line closest to the query, if it is within
+    /// `max_distance` edits.
+    Fuzzy {
+        query: Vec<char>,
+        max_distance: usize,
+        ignore_case: bool,
+    },
+}
+
+/// How much of a line a match has to cover.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchMode {
+    /// Anywhere in the line.
+    Substring,
+    /// A whole word: neither side of the match may touch a word character.
+    Word,
+    /// The whole line.
+    Line,
+}
+
+impl Matcher {
+    pub fn new(pattern: Pattern, mode: MatchMode) -> Matcher {
+        Matcher {
+            pattern,
+            mode,
+            literal: None,
+        }
+    }
+
+    /// Builds the matcher for `config`, reading any pattern files it names.
+    pub fn build(config: &Config) -> Result<Matcher, Box<dyn Error>> {
+        let mut patterns = config.patterns.clone();
+        for path in &config.pattern_files {
+            patterns.extend(read_patterns(path)?);
+        }
+
+        let ignore_case = config.ignore_case
+            || (config.smart_case && !patterns.iter().any(|p| has_uppercase(p, config.regex)));
+
+        // Like grep, -x wins over -w.
+        let mode = if config.line_regexp {
+            MatchMode::Line
+        } else if config.word_regexp {
+            MatchMode::Word
+        } else {
+            MatchMode::Substring
+        };
+
+        if let Some(max_distance) = config.fuzzy {
+            let [query] = patterns.as_slice() else {
+                return Err("--fuzzy needs exactly one query".into());
+            };
+            let pattern = Pattern::Fuzzy {
+                query: query.chars().collect(),
+                max_distance,
+                ignore_case,
+            };
+            return Ok(Matcher::new(pattern, mode));
+        }
+
+        let pattern = match patterns.as_slice() {
+            // An empty pattern file selects nothing, so there's nothing to
+            // compile.
+            [] => Pattern::literals(&patterns)?,
+            _ if !config.regex && ignore_case => {
+                Pattern::CaseInsensitive(patterns.iter().map(|query| fold::fold(query)).collect())
+            }
+            _ if !config.regex => Pattern::literals(&patterns)?,
+            _ => {
+                let mut alternation = alternation(&patterns);
+                if mode == MatchMode::Line {
+                    alternation = format!("^(?:{alternation})$");
+                }
+
+                // Across lines, ^ and $ still mean the start and end of a
+                // line, which may end with \r\n as well as \n.
+                let pattern = RegexBuilder::new(&alternation)
+                    .case_insensitive(ignore_case)
+                    .multi_line(config.multiline)
+                    .crlf(config.multiline)
+                    .build()
+                    .map_err(|err| {
+                        // Report the pattern at fault rather than the whole
+                        // alternation, when one can be found.
+                        patterns
+                            .iter()
+                            .find_map(|pattern| Regex::new(pattern).err())
+                            .unwrap_or(err)
+                    })?;
+                Pattern::Regex(pattern)
+            }
+        };
+
+        let mut matcher = Matcher::new(pattern, mode);
+        if let [query] = patterns.as_slice() {
+            if !config.regex && !ignore_case {
+                matcher.literal = Some(query.clone());
+            }
+        }
+        Ok(matcher)
+    }
+
+    /// Returns the query if `literal::Query` finds the same matches in a
+    /// whole buffer as `find_all` does in each of its lines: there's a single
+    /// literal query, which can be anywhere in a line but can't span lines.
+    pub fn whole_buffer_query(&self) -> Option<&str> {
+        let query = self.literal.as_deref()?;
+        let fits =
+            self.mode == MatchMode::Substring && !query.is_empty() && !query.contains(['\n', '\r']);
+        fits.then_some(query)
+    }
+
+    /// Returns the byte range within `line` of every non-overlapping match,
+    /// from left to right.
+    pub fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+        match self.mode {
+            MatchMode::Substring => self.pattern.find_all(line),
+            MatchMode::Word => self.find_words(line),
+            // Matches are leftmost-longest for literals, exact for folded
+            // text, and anchored at both ends for a regex, so if anything
+            // covers the whole line, the first match from the start does.
+            MatchMode::Line => self
+                .pattern
+                .find_at(line, 0)
+                .filter(|found| *found == (0..line.len()))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Returns an error if `replacement` refers to a capture group that the
+    /// pattern doesn't have, which would otherwise quietly expand to nothing.
+    /// Only a regular expression has groups to refer to.
+    pub fn check_replacement(&self, replacement: &str) -> Result<(), String> {
+        let Pattern::Regex(pattern) = &self.pattern else {
+            return Ok(());
+        };
+
+        for name in group_refs(replacement) {
+            let exists = match name.parse::<usize>() {
+                Ok(index) => index < pattern.captures_len(),
+                Err(_) => pattern.capture_names().any(|group| group == Some(name)),
+            };
+            if exists {
+                continue;
+            }
+
+            let mut message =
+                format!("--replace refers to group '{name}', which the pattern doesn't have");
+            // "$1st" is group "1st", not group 1 followed by "st".
+            let digits = name.len() - name.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits > 0 {
+                let (index, rest) = name.split_at(digits);
+                message.push_str(&format!(
+                    "; write ${{{index}}}{rest} for group {index} followed by '{rest}'"
+                ));
+            }
+            return Err(message);
+        }
+
+        Ok(())
+    }
+
+    /// Returns `line` with each of `submatches` replaced by `replacement`,
+    /// along with where each replacement ended up in the new line.
+    ///
+    /// If `expand` is set and the pattern is a regular expression, `$1` or
+    /// `${name}` in `replacement` stands for what that group captured.
+    pub fn replace(
+        &self,
+        line: &str,
+        submatches: &[Range<usize>],
+        replacement: &str,
+        expand: bool,
+    ) -> (String, Vec<Range<usize>>) {
+        let mut replaced = String::with_capacity(line.len());
+        let mut ranges = Vec::with_capacity(submatches.len());
+        let mut written = 0;
+
+        for submatch in submatches {
+            replaced.push_str(&line[written..submatch.start]);
+            let start = replaced.len();
+
+            match &self.pattern {
+                Pattern::Regex(pattern) if expand => {
+                    if let Some(captures) = pattern.captures_at(line, submatch.start) {
+                        captures.expand(replacement, &mut replaced);
+                    }
+                }
+                _ => replaced.push_str(replacement),
+            }
+
+            ranges.push(start..replaced.len());
+            written = submatch.end;
+        }
+        replaced.push_str(&line[written..]);
+
+        (replaced, ranges)
+    }
+
+    /// Like `find_all`, but skips matches that start or end inside a word.
+    /// After a rejected match the search resumes one character later, since
+    /// a shorter or later match overlapping it may still stand alone.
+    fn find_words(&self, line: &str) -> Vec<Range<usize>> {
+        let mut found = Vec::new();
+        let mut start = 0;
+
+        while start <= line.len() {
+            let Some(candidate) = self.pattern.find_at(line, start) else {
+                break;
+            };
+
+            let before = line[..candidate.start].chars().next_back();
+            let after = line[candidate.end..].chars().next();
+            let stands_alone =
+                !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char);
+
+            start = if stands_alone && !candidate.is_empty() {
+                candidate.end
+            } else {
+                next_char(line, candidate.start)
+            };
+            if stands_alone {
+                found.push(candidate);
+            }
+        }
+
+        found
+    }
+
+    pub fn search<'a>(&self, contents: &'a str) -> Vec<Match<'a>> {
+        let mut results = Vec::new();
+
+        for mut line in lines(contents) {
+            line.submatches = self.find_all(&line.text);
+
+            if !line.submatches.is_empty() {
+                results.push(line);
+            }
+        }
+
+        results
+    }
+}
+
+impl Pattern {
+    /// Matches any of `patterns` literally, preferring the longest match
+    /// where several start at the same place.
+    pub fn literals<P: AsRef<[u8]>>(patterns: &[P]) -> Result<Pattern, BuildError> {
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(patterns)?;
+        Ok(Pattern::Literal(automaton))
+    }
+
+    fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+        match self {
+            Pattern::Literal(automaton) => automaton
+                .find_iter(line)
+                .map(|found| found.range())
+                // The automaton works on bytes, so an empty pattern also
+                // matches inside multi-byte characters.
+                .filter(|found| !found.is_empty() || line.is_char_boundary(found.start))
+                .collect(),
+            Pattern::CaseInsensitive(queries) => match queries.as_slice() {
+                [query] => fold::find_all(query, line),
+                _ => fold::find_all_of(queries, line),
+            },
+            Pattern::Regex(pattern) => pattern.find_iter(line).map(|found| found.range()).collect(),
+            Pattern::Fuzzy { .. } => self.find_at(line, 0).into_iter().collect(),
+        }
+    }
+
+    /// Returns the first match in `line` that starts at or after byte
+    /// `start`, which must be on a character boundary.
+    fn find_at(&self, line: &str, start: usize) -> Option<Range<usize>> {
+        match self {
+            Pattern::Literal(automaton) => automaton
+                .find(Input::new(line).span(start..line.len()))
+                .map(|found| found.range()),
+            Pattern::CaseInsensitive(queries) => match queries.as_slice() {
+                [query] => fold::find_at(query, line, start),
+                _ => fold::find_any_at(queries, line, start),
+            },
+            Pattern::Regex(pattern) => pattern.find_at(line, start).map(|found| found.range()),
+            Pattern::Fuzzy {
+                query,
+                max_distance,
+                ignore_case,
+            } => fuzzy::find(query, &line[start..], *max_distance, *ignore_case)
+                .map(|(_, found)| found.start + start..found.end + start),
+        }
+    }
+}
+
+/// Word characters as Unicode sees them: letters and digits in any script,
+/// plus the underscore.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The byte index of the character after the one at `index`, or just past
+/// the end of `line` if `index` is already there.
+fn next_char(line: &str, index: usize) -> usize {
+    index + line[index..].chars().next().map_or(1, char::len_utf8)
+}
+
+/// Reads one pattern per line from the file at `path`.
+fn read_patterns(path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    Ok(lines(&contents).map(|line| line.text.to_string()).collect())
+}
+
+/// Returns the name or number of every capture group that `replacement`
+/// refers to, read the way `regex::Captures::expand` reads them: `$$` is a
+/// dollar sign, `${name}` runs to the closing brace, and `$name` takes as many
+/// letters, digits and underscores as follow it.
+fn group_refs(replacement: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = replacement;
+
+    while let Some(dollar) = rest.find('$') {
+        rest = &rest[dollar + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            rest = after;
+            continue;
+        }
+
+        let name = match rest.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => {
+                    rest = &braced[end + 1..];
+                    &braced[..end]
+                }
+                // Without a closing brace, it's all just text.
+                None => continue,
+            },
+            None => {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                let name = &rest[..end];
+                rest = &rest[end..];
+                name
+            }
+        };
+        if !name.is_empty() {
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+/// Joins `patterns` into one regular expression that matches any of them.
+fn alternation(patterns: &[String]) -> String {
+    match patterns {
+        [pattern] => pattern.clone(),
+        _ => patterns
+            .iter()
+            .map(|pattern| format!("(?:{pattern})"))
+            .collect::<Vec<_>>()
+            .join("|"),
+    }
+}
+
+/// Whether `query` has any uppercase letters, for smart case. In a regular
+/// expression, escaped characters such as the `W` in `\W` don't count.
+fn has_uppercase(query: &str, regex: bool) -> bool {
+    let mut chars = query.chars();
+
+    while let Some(c) = chars.next() {
+        if regex && c == '\\' {
+            chars.next();
+        } else if c.is_uppercase() {
+            return true;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_all_positions() {
+        let line = "Trust a rusty RUST.";
+
+        let literal = Pattern::literals(&["rust"]).unwrap();
+        assert_eq!(vec![1..5, 8..12], literal.find_all(line));
+
+        let caseless = Pattern::CaseInsensitive(vec![fold::fold("rust")]);
+        assert_eq!(vec![1..5, 8..12, 14..18], caseless.find_all(line));
+
+        let regex = Pattern::Regex(Regex::new(r"[Rr]ust\w*").unwrap());
+        assert_eq!(vec![1..5, 8..13], regex.find_all(line));
+    }
+
+    #[test]
+    fn pattern_sets() {
+        let line = "error: disk full; warning: disk nearly full";
+
+        let literals = Pattern::literals(&["disk", "disk nearly", "full", "warning"]).unwrap();
+        assert_eq!(
+            vec![7..11, 12..16, 18..25, 27..38, 39..43],
+            literals.find_all(line)
+        );
+
+        let regex = Pattern::Regex(
+            Regex::new(&alternation(&[String::from(r"\w+:"), String::from("full")])).unwrap(),
+        );
+        assert_eq!(vec![0..6, 12..16, 18..26, 39..43], regex.find_all(line));
+
+        let nothing = Pattern::literals::<&str>(&[]).unwrap();
+        assert!(nothing.find_all(line).is_empty());
+    }
+
+    #[test]
+    fn caseless_pattern_sets_fold_fully() {
+        let config = Config::builder()
+            .pattern("ß")
+            .pattern("x")
+            .ignore_case(true)
+            .build()
+            .unwrap();
+        let matcher = Matcher::build(&config).unwrap();
+        assert_eq!(vec![2..4, 5..6], matcher.find_all("MASS X"));
+    }
+
+    #[test]
+    fn replacements_only_refer_to_groups_that_exist() {
+        let matcher = Matcher::new(
+            Pattern::Regex(Regex::new(r"(\w+)@(?<host>\w+)").unwrap()),
+            MatchMode::Substring,
+        );
+        for replacement in ["$1 at $host", "${1}X", "$0", "$$5", "${2}", "cost: $"] {
+            assert_eq!(
+                Ok(()),
+                matcher.check_replacement(replacement),
+                "{replacement}"
+            );
+        }
+
+        assert_eq!(
+            Err(String::from(
+                "--replace refers to group 'user', which the pattern doesn't have"
+            )),
+            matcher.check_replacement("$user")
+        );
+        assert_eq!(
+            Err(String::from(
+                "--replace refers to group '1X', which the pattern doesn't have; \
+                 write ${1}X for group 1 followed by 'X'"
+            )),
+            matcher.check_replacement("$1X")
+        );
+        assert!(matcher.check_replacement("${3}").is_err());
+
+        // Literal patterns have no groups, so there's nothing to refer to.
+        let literal = Matcher::new(Pattern::literals(&["a"]).unwrap(), MatchMode::Substring);
+        assert_eq!(Ok(()), literal.check_replacement("$1X"));
+    }
+
+    #[test]
+    fn empty_pattern_matches_on_char_boundaries() {
+        let empty = Pattern::literals(&[""]).unwrap();
+        assert_eq!(vec![0..0, 2..2, 3..3], empty.find_all("éa"));
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn whole_words() {
+        let words = |pattern| Matcher::new(pattern, MatchMode::Word);
+        let line = "to Toad, tomorrow: to_do; go to";
+
+        let literal = words(Pattern::literals(&["to"]).unwrap());
+        assert_eq!(vec![0..2, 29..31], literal.find_all(line));
+
+        let caseless = words(Pattern::CaseInsensitive(vec![fold::fold("TO")]));
+        assert_eq!(vec![0..2, 3..5, 11..13], caseless.find_all("to To Toad to"));
+
+        let regex = words(Pattern::Regex(Regex::new(r"t\w").unwrap()));
+        assert_eq!(vec![0..2, 29..31], regex.find_all(line));
+
+        // A rejected match doesn't hide a later one that overlaps it.
+        let overlapping = words(Pattern::literals(&["a a"]).unwrap());
+        assert_eq!(vec![3..6], overlapping.find_all("ba a a"));
+
+        // Letters in other scripts are word characters too.
+        let unicode = words(Pattern::literals(&["über"]).unwrap());
+        assert!(unicode.find_all("drüber").is_empty());
+        assert_eq!(vec![4..9], unicode.find_all("ist über alles"));
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn whole_lines() {
+        let lines = |pattern| Matcher::new(pattern, MatchMode::Line);
+
+        let literal = lines(Pattern::literals(&["to", "to be"]).unwrap());
+        assert_eq!(vec![0..5], literal.find_all("to be"));
+        assert!(literal.find_all("to be or not").is_empty());
+
+        let regex = lines(Pattern::Regex(Regex::new(r"^(?:to|to be)$").unwrap()));
+        assert_eq!(vec![0..5], regex.find_all("to be"));
+    }
+
+    #[test]
+    fn replacements() {
+        let line = "to be or not to be";
+
+        let literal = Matcher::new(Pattern::literals(&["be"]).unwrap(), MatchMode::Word);
+        let submatches = literal.find_all(line);
+        assert_eq!(
+            (String::from("to $1 or not to $1"), vec![3..5, 16..18]),
+            literal.replace(line, &submatches, "$1", true)
+        );
+
+        let regex = Matcher::new(
+            Pattern::Regex(Regex::new(r"(?<verb>\w+) (\w+)").unwrap()),
+            MatchMode::Substring,
+        );
+        let submatches = regex.find_all(line);
+        assert_eq!(
+            String::from("be-to not-or be-to"),
+            regex.replace(line, &submatches, "${2}-$verb", true).0
+        );
+        assert_eq!(
+            String::from("$2 $2 $2"),
+            regex.replace(line, &submatches, "$2", false).0
+        );
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(!has_uppercase("rust", false));
+        assert!(has_uppercase("Rust", false));
+        assert!(!has_uppercase(r"\Wrust\S", true));
+        assert!(has_uppercase(r"\W", false));
+    }
+}
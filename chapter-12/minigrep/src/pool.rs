@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+/// Runs `work` on each of `items` using up to `threads` worker threads, and
+/// hands the results to `each` in the same order as `items`.
+///
+/// `each` runs on the calling thread, so it can write to stdout without the
+/// output for different items interleaving. Results that finish early wait
+/// until everything before them has been handled. If `each` returns
+/// `Ok(false)` or an error, the items that haven't been started yet are
+/// abandoned.
+pub fn for_each_ordered<T, R, E>(
+    threads: usize,
+    items: Vec<T>,
+    work: impl Fn(T) -> R + Sync,
+    mut each: impl FnMut(R) -> Result<bool, E>,
+) -> Result<(), E>
+where
+    T: Send,
+    R: Send,
+{
+    let threads = threads.clamp(1, items.len().max(1));
+
+    let (job_sender, job_receiver) = mpsc::channel();
+    for job in items.into_iter().enumerate() {
+        job_sender.send(job).unwrap();
+    }
+    drop(job_sender);
+
+    let job_receiver = Mutex::new(job_receiver);
+    let (result_sender, result_receiver) = mpsc::channel();
+    let stop = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let result_sender = result_sender.clone();
+            let (job_receiver, stop, work) = (&job_receiver, &stop, &work);
+
+            scope.spawn(move || loop {
+                // Take the lock only long enough to receive, so the other
+                // workers can pick up jobs while this one is busy.
+                let job = job_receiver.lock().unwrap().recv();
+
+                let Ok((index, item)) = job else {
+                    break;
+                };
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                if result_sender.send((index, work(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(result_sender);
+
+        let mut pending = HashMap::new();
+        let mut next = 0;
+
+        for (index, result) in result_receiver.iter() {
+            pending.insert(index, result);
+
+            while let Some(result) = pending.remove(&next) {
+                next += 1;
+
+                match each(result) {
+                    Ok(true) => {}
+                    finished => {
+                        stop.store(true, Ordering::Relaxed);
+                        return finished.map(|_| ());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn results_keep_their_order() {
+        let items: Vec<u64> = (0..20).collect();
+        let mut seen = Vec::new();
+
+        let finished: Result<(), ()> = for_each_ordered(
+            4,
+            items,
+            |item| {
+                // Make the early items finish last.
+                thread::sleep(Duration::from_millis(20 - item));
+                item * 10
+            },
+            |result| {
+                seen.push(result);
+                Ok(true)
+            },
+        );
+
+        assert!(finished.is_ok());
+        assert_eq!((0..20).map(|item| item * 10).collect::<Vec<_>>(), seen);
+    }
+
+    #[test]
+    fn stops_when_asked() {
+        let mut seen = Vec::new();
+
+        let finished = for_each_ordered(
+            2,
+            (0..100).collect(),
+            |item: i32| item,
+            |result| {
+                seen.push(result);
+                if result == 3 {
+                    Err("enough")
+                } else {
+                    Ok(true)
+                }
+            },
+        );
+
+        assert_eq!(Err("enough"), finished);
+        assert_eq!(vec![0, 1, 2, 3], seen);
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::json::{self, Stats};
+use crate::{Config, Match};
+
+// The same colours GNU grep uses by default.
+const MATCH_COLOR: &str = "\x1b[1;31m";
+const PATH_COLOR: &str = "\x1b[35m";
+const NUMBER_COLOR: &str = "\x1b[32m";
+const SEPARATOR_COLOR: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Writes the results of a search the way grep does: selected lines, any
+/// context lines around them, and `--` between groups that aren't adjacent.
+///
+/// Lines are fed in one at a time, in order, so the printer only has to
+/// remember the last few lines for before-context.
+///
+/// With `--json`, the same lines are written as JSON records instead.
+pub struct Printer<W> {
+    out: W,
+    line_number: bool,
+    byte_offset: bool,
+    before_context: usize,
+    after_context: usize,
+    color: bool,
+    json: bool,
+    /// The current file's path, if lines should be prefixed with it.
+    path: Option<String>,
+    /// The current file's path, for JSON records.
+    json_path: String,
+    /// Whether the JSON `begin` record has been written for the current file.
+    began: bool,
+    started: Instant,
+    file_stats: Stats,
+    total_stats: Stats,
+    /// Unselected lines that may be needed as before-context, oldest first.
+    before: VecDeque<(usize, usize, String)>,
+    /// How many more unselected lines to print as after-context.
+    after_remaining: usize,
+    /// Line number of the last line printed from the current file.
+    last_printed: Option<usize>,
+    printed_any: bool,
+}
+
+impl<W: Write> Printer<W> {
+    /// Creates a printer that writes to `out`, highlighting with ANSI colours
+    /// if `color` is set.
+    pub fn new(config: &Config, color: bool, out: W) -> Printer<W> {
+        Printer {
+            out,
+            line_number: config.line_number,
+            byte_offset: config.byte_offset,
+            before_context: config.before_context,
+            after_context: config.after_context,
+            color,
+            json: config.json,
+            path: None,
+            json_path: String::new(),
+            began: false,
+            started: Instant::now(),
+            file_stats: Stats::default(),
+            total_stats: Stats::default(),
+            before: VecDeque::with_capacity(config.before_context),
+            after_remaining: 0,
+            last_printed: None,
+            printed_any: false,
+        }
+    }
+
+    /// Starts printing results for the file at `path`. Lines are prefixed
+    /// with the path if `show_path` is set.
+    pub fn begin(&mut self, path: &Path, show_path: bool) {
+        self.json_path = path.display().to_string();
+        self.path = show_path.then(|| self.json_path.clone());
+        self.began = false;
+        self.started = Instant::now();
+        self.file_stats = Stats {
+            searches: 1,
+            ..Stats::default()
+        };
+        self.before.clear();
+        self.after_remaining = 0;
+        self.last_printed = None;
+    }
+
+    /// Finishes the current file, writing its JSON `end` record if anything
+    /// was printed for it.
+    pub fn end(&mut self) -> io::Result<()> {
+        self.file_stats.elapsed = self.started.elapsed();
+        if self.file_stats.matched_lines > 0 {
+            self.file_stats.searches_with_match = 1;
+        }
+
+        if self.json && self.began {
+            self.write_json(json::end(&self.json_path, self.file_stats))?;
+        }
+        self.total_stats.add(&self.file_stats);
+
+        Ok(())
+    }
+
+    /// Writes the JSON `summary` record, once every file has been searched.
+    pub fn finish(&mut self, elapsed_total: Duration) -> io::Result<()> {
+        if self.json {
+            self.write_json(json::summary(elapsed_total, self.total_stats))?;
+        }
+
+        Ok(())
+    }
+
+    /// Handles the next line of the current file. `selected` lines are always
+    /// printed; other lines are printed only if they fall within the context
+    /// of a selected line.
+    pub fn line(&mut self, line: &Match, selected: bool) -> io::Result<()> {
+        let offset = line.byte_range.start;
+
+        if selected {
+            self.file_stats.matched_lines += 1;
+            self.file_stats.matches += line.submatches.len();
+
+            while let Some((number, offset, text)) = self.before.pop_front() {
+                self.write_line(number, offset, &text, &[], false)?;
+            }
+            self.write_line(line.line_number, offset, &line.text, &line.submatches, true)?;
+            self.after_remaining = self.after_context;
+        } else if self.after_remaining > 0 {
+            self.after_remaining -= 1;
+            self.write_line(line.line_number, offset, &line.text, &[], false)?;
+        } else if self.before_context > 0 {
+            if self.before.len() == self.before_context {
+                self.before.pop_front();
+            }
+            self.before
+                .push_back((line.line_number, offset, line.text.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Writes out everything that `other` printed, as if it had been printed
+    /// by this printer. This lets files be searched in parallel, each with
+    /// its own printer, and still be output one after another.
+    pub fn append(&mut self, other: Printer<Vec<u8>>) -> io::Result<()> {
+        if other.printed_any && self.printed_any && self.has_context() {
+            self.write_colored(SEPARATOR_COLOR, "--")?;
+            writeln!(self.out)?;
+        }
+
+        self.out.write_all(&other.out)?;
+        self.printed_any |= other.printed_any;
+        self.total_stats.add(&other.total_stats);
+
+        Ok(())
+    }
+
+    /// Prints the number of selected lines in the current file.
+    pub fn count(&mut self, count: usize) -> io::Result<()> {
+        if let Some(path) = self.path.take() {
+            self.write_colored(PATH_COLOR, &path)?;
+            self.write_colored(SEPARATOR_COLOR, ":")?;
+            self.path = Some(path);
+        }
+        writeln!(self.out, "{count}")
+    }
+
+    /// Prints just `path`, for when only the names of files are wanted.
+    pub fn path(&mut self, path: &Path) -> io::Result<()> {
+        self.write_colored(PATH_COLOR, &path.display().to_string())?;
+        writeln!(self.out)
+    }
+
+    fn write_line(
+        &mut self,
+        line_number: usize,
+        byte_offset: usize,
+        text: &str,
+        submatches: &[Range<usize>],
+        selected: bool,
+    ) -> io::Result<()> {
+        if self.json {
+            if !self.began {
+                self.write_json(json::begin(&self.json_path))?;
+                self.began = true;
+            }
+
+            let path = &self.json_path;
+            let record = json::line(path, selected, line_number, byte_offset, text, submatches);
+            return self.write_json(record);
+        }
+
+        let follows_last = self
+            .last_printed
+            .is_some_and(|last| last + 1 == line_number);
+
+        if self.has_context() && self.printed_any && !follows_last {
+            self.write_colored(SEPARATOR_COLOR, "--")?;
+            writeln!(self.out)?;
+        }
+
+        let separator = if selected { ":" } else { "-" };
+
+        if let Some(path) = self.path.take() {
+            self.write_colored(PATH_COLOR, &path)?;
+            self.write_colored(SEPARATOR_COLOR, separator)?;
+            self.path = Some(path);
+        }
+        if self.line_number {
+            self.write_colored(NUMBER_COLOR, &line_number.to_string())?;
+            self.write_colored(SEPARATOR_COLOR, separator)?;
+        }
+        if self.byte_offset {
+            self.write_colored(NUMBER_COLOR, &byte_offset.to_string())?;
+            self.write_colored(SEPARATOR_COLOR, separator)?;
+        }
+
+        let mut written = 0;
+        for submatch in submatches {
+            write!(self.out, "{}", &text[written..submatch.start])?;
+            self.write_colored(MATCH_COLOR, &text[submatch.clone()])?;
+            written = submatch.end;
+        }
+        writeln!(self.out, "{}", &text[written..])?;
+
+        self.last_printed = Some(line_number);
+        self.printed_any = true;
+
+        Ok(())
+    }
+
+    fn write_json(&mut self, record: Value) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, &record)?;
+        writeln!(self.out)
+    }
+
+    fn has_context(&self) -> bool {
+        self.before_context > 0 || self.after_context > 0
+    }
+
+    fn write_colored(&mut self, color: &str, text: &str) -> io::Result<()> {
+        if self.color && !text.is_empty() {
+            write!(self.out, "{color}{text}{RESET}")
+        } else {
+            write!(self.out, "{text}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lines;
+
+    fn config(args: &[&str]) -> Config {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Config::build_without_file(&args).unwrap()
+    }
+
+    fn print(args: &[&str], selected: &[usize]) -> String {
+        let config = config(args);
+        let contents = "one\ntwo\nthree\nfour\nfive\nsix\nseven\n";
+
+        let mut printer = Printer::new(&config, false, Vec::new());
+        for line in lines(contents) {
+            let is_selected = selected.contains(&line.line_number);
+            printer.line(&line, is_selected).unwrap();
+        }
+
+        String::from_utf8(printer.out).unwrap()
+    }
+
+    #[test]
+    fn context_groups_are_separated() {
+        let output = print(&["minigrep", "-n", "-C1", "q", "f"], &[2, 6]);
+        assert_eq!(
+            "1-one\n2:two\n3-three\n--\n5-five\n6:six\n7-seven\n",
+            output
+        );
+    }
+
+    #[test]
+    fn adjacent_context_is_merged() {
+        let output = print(&["minigrep", "-b", "-A2", "q", "f"], &[1, 4]);
+        assert_eq!("0:one\n4-two\n8-three\n14:four\n19-five\n24-six\n", output);
+    }
+
+    #[test]
+    fn highlights_submatches() {
+        let config = config(&["minigrep", "-n", "o"]);
+        let mut printer = Printer::new(&config, true, Vec::new());
+        printer.begin(Path::new("poem.txt"), true);
+
+        let mut line = lines("a frog on a log").next().unwrap();
+        line.submatches = vec![4..5, 7..8, 13..14];
+        printer.line(&line, true).unwrap();
+
+        assert_eq!(
+            "\x1b[35mpoem.txt\x1b[0m\x1b[36m:\x1b[0m\x1b[32m1\x1b[0m\x1b[36m:\x1b[0m\
+             a fr\x1b[1;31mo\x1b[0mg \x1b[1;31mo\x1b[0mn a l\x1b[1;31mo\x1b[0mg\n",
+            String::from_utf8(printer.out).unwrap()
+        );
+    }
+
+    #[test]
+    fn appended_output_is_separated() {
+        let config = config(&["minigrep", "-A1", "q"]);
+        let line = lines("seven").next().unwrap();
+
+        let mut printer = Printer::new(&config, false, Vec::new());
+        for _ in 0..2 {
+            let mut file_printer = Printer::new(&config, false, Vec::new());
+            file_printer.line(&line, true).unwrap();
+            printer.append(file_printer).unwrap();
+        }
+
+        assert_eq!(
+            "seven\n--\nseven\n",
+            String::from_utf8(printer.out).unwrap()
+        );
+    }
+
+    #[test]
+    fn json_records() {
+        let config = config(&["minigrep", "--json", "-A1", "o"]);
+        let mut printer = Printer::new(&config, false, Vec::new());
+        printer.begin(Path::new("poem.txt"), false);
+
+        for mut line in lines("a frog on a log\nthen a toad") {
+            if line.line_number == 1 {
+                line.submatches = vec![4..5, 7..8, 13..14];
+            }
+            printer.line(&line, line.line_number == 1).unwrap();
+        }
+        printer.end().unwrap();
+        printer.finish(Duration::ZERO).unwrap();
+
+        let output = String::from_utf8(printer.out).unwrap();
+        let records: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let types: Vec<&str> = records
+            .iter()
+            .map(|record| record["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(vec!["begin", "match", "context", "end", "summary"], types);
+
+        let found = &records[1]["data"];
+        assert_eq!("poem.txt", found["path"]["text"]);
+        assert_eq!(1, found["line_number"]);
+        assert_eq!("o", found["submatches"][2]["match"]["text"]);
+        assert_eq!(13, found["submatches"][2]["start"]);
+        assert_eq!(3, records[4]["data"]["stats"]["matches"]);
+    }
+}
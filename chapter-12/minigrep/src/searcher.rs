@@ -0,0 +1,521 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::encoding::{self, Decoded, Encoding};
+use crate::fields::{Columns, Fields};
+use crate::matcher::Matcher;
+use crate::{lines, walk, Config, LineReader, Match};
+
+/// A search prepared from a `Config`, which can be run over any number of
+/// inputs.
+///
+/// A `Searcher` selects lines exactly as the `minigrep` binary does, using
+/// the config's patterns and its case, `-w`, `-x`, `-v`, `-U`, `--field` and
+/// `--encoding` settings. How the lines are printed is left to the caller, so
+/// settings such as line numbers or context have no effect here.
+///
+/// ```
+/// use minigrep::{Config, Searcher};
+///
+/// let config = Config::builder().pattern("frog").build().unwrap();
+/// let searcher = Searcher::new(&config).unwrap();
+///
+/// let found: Vec<_> = searcher
+///     .search("I'm nobody!\nHow public, like a frog")
+///     .map(|line| line.line_number)
+///     .collect();
+/// assert_eq!(vec![2], found);
+/// ```
+pub struct Searcher {
+    matcher: Matcher,
+    invert_match: bool,
+    replace: Option<String>,
+    /// Whether `replace` can refer to capture groups.
+    regex: bool,
+    multiline: bool,
+    fields: Option<Fields>,
+    encoding: Option<Encoding>,
+}
+
+impl Searcher {
+    /// Compiles the patterns in `config`, reading any pattern files it names.
+    pub fn new(config: &Config) -> Result<Searcher, Box<dyn Error>> {
+        let matcher = Matcher::build(config)?;
+        if let Some(replace) = config.replace.as_deref().filter(|_| config.regex) {
+            matcher.check_replacement(replace)?;
+        }
+
+        Ok(Searcher {
+            matcher,
+            invert_match: config.invert_match,
+            replace: config.replace.clone(),
+            regex: config.regex,
+            multiline: config.multiline,
+            fields: Fields::new(config),
+            encoding: config.encoding,
+        })
+    }
+
+    /// Finds the matches in `line`, recording them in its `submatches`, and
+    /// returns whether the line is selected.
+    ///
+    /// This doesn't know which file `line` came from, so it can't be used to
+    /// search fields; `selector` makes something that can.
+    pub(crate) fn select(&self, line: &mut Match) -> bool {
+        line.submatches = self.matcher.find_all(&line.text);
+        line.submatches.is_empty() == self.invert_match
+    }
+
+    /// Returns the query to search a whole buffer for with `literal::Query`,
+    /// if doing so selects the same lines as searching each line does.
+    pub(crate) fn whole_buffer_query(&self) -> Option<&str> {
+        if self.invert_match || self.multiline || self.fields.is_some() {
+            return None;
+        }
+        self.matcher.whole_buffer_query()
+    }
+
+    /// Returns a `Selector` for the lines of a single file.
+    pub(crate) fn selector(&self) -> Selector<'_> {
+        Selector {
+            searcher: self,
+            columns: Columns::default(),
+        }
+    }
+
+    /// Substitutes the config's replacement text for every match in `line`,
+    /// which must have come from this searcher. Lines are returned as they
+    /// are if there is no replacement or nothing to replace.
+    ///
+    /// With `--field`, only the field is replaced in, so that a pattern is
+    /// matched against the same text as when the line was selected.
+    pub fn replace<'a>(&self, line: Match<'a>) -> Match<'a> {
+        let Some(replacement) = &self.replace else {
+            return line;
+        };
+        let Some(first) = line.submatches.first() else {
+            return line;
+        };
+        let span = match &self.fields {
+            Some(fields) => match fields.span_at(&line.text, first.start) {
+                Some(span) => span,
+                None => return line,
+            },
+            None => 0..line.text.len(),
+        };
+
+        let submatches: Vec<Range<usize>> = line
+            .submatches
+            .iter()
+            .map(|found| found.start - span.start..found.end - span.start)
+            .collect();
+        let (replaced, submatches) = self.matcher.replace(
+            &line.text[span.clone()],
+            &submatches,
+            replacement,
+            self.regex,
+        );
+        let text = [&line.text[..span.start], &replaced, &line.text[span.end..]].concat();
+
+        Match {
+            text: Cow::Owned(text),
+            submatches: submatches
+                .into_iter()
+                .map(|found| span.start + found.start..span.start + found.end)
+                .collect(),
+            ..line
+        }
+    }
+
+    /// Returns an error if `header`, the first line of a CSV file, lacks a
+    /// column that `--field` or `--select` names. Unlike a `Selector`,
+    /// `lines` can't report this itself.
+    pub(crate) fn check_header(&self, header: &str) -> io::Result<()> {
+        match &self.fields {
+            Some(fields) if fields.has_header() => check_columns(fields, &fields.columns(header)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the selected lines of `contents`.
+    pub fn search<'a>(&'a self, contents: &'a str) -> impl Iterator<Item = Match<'a>> + 'a {
+        self.lines(contents)
+            .filter_map(|(line, selected)| selected.then_some(line))
+    }
+
+    /// Returns every line of `contents`, with its submatches, and whether it
+    /// is selected.
+    ///
+    /// In multiline mode the patterns are matched against the whole of
+    /// `contents`, and every line that a match touches is selected, with the
+    /// part of the match on that line as its submatch.
+    pub(crate) fn lines<'a>(
+        &'a self,
+        contents: &'a str,
+    ) -> impl Iterator<Item = (Match<'a>, bool)> + 'a {
+        let mut spans = self.multiline.then(|| Spans {
+            found: self.matcher.find_all(contents),
+            next: 0,
+        });
+        let mut selector = self.selector();
+
+        lines(contents).map(move |mut line| {
+            let selected = match &mut spans {
+                Some(spans) => spans.cover(&mut line, contents) != self.invert_match,
+                None => selector.select(&mut line),
+            };
+            (line, selected)
+        })
+    }
+
+    /// Returns the selected lines read from `reader`, which is read a line
+    /// at a time as the iterator advances.
+    ///
+    /// Text in other encodings is transcoded to UTF-8 first, as `minigrep`
+    /// does, and binary input has no lines selected.
+    pub fn search_reader<R: Read>(&self, reader: R) -> Matches<'_, R> {
+        Matches {
+            selector: self.selector(),
+            encoding: self.encoding,
+            reader: Some(BufReader::new(reader)),
+            lines: None,
+        }
+    }
+
+    /// Returns the selected lines of the file at `path`.
+    pub fn search_path(&self, path: impl AsRef<Path>) -> io::Result<Matches<'_, File>> {
+        Ok(self.search_reader(File::open(path)?))
+    }
+}
+
+/// Selects the lines of one file, remembering what it needs to know about
+/// the file from one line to the next: where its columns are, when searching
+/// a CSV field.
+pub(crate) struct Selector<'s> {
+    searcher: &'s Searcher,
+    columns: Columns,
+}
+
+impl Selector<'_> {
+    /// Like `Searcher::select`, but only matches the searched field if there
+    /// is one, in which case the header of a CSV file is never selected.
+    pub(crate) fn select(&mut self, line: &mut Match) -> bool {
+        let Some(fields) = &self.searcher.fields else {
+            return self.searcher.select(line);
+        };
+
+        line.submatches.clear();
+        if fields.has_header() && line.line_number == 1 {
+            self.columns = fields.columns(&line.text);
+            return false;
+        }
+
+        let Some(field) = fields.field(&line.text, &self.columns) else {
+            // A line without the field can't match it.
+            return self.searcher.invert_match;
+        };
+        let found = self.searcher.matcher.find_all(&field.value);
+        let selected = found.is_empty() == self.searcher.invert_match;
+
+        // Matches can only be shown if the field appears in the line as it
+        // is.
+        if let Some(start) = field.start {
+            line.submatches = found
+                .into_iter()
+                .map(|found| start + found.start..start + found.end)
+                .collect();
+        }
+        selected
+    }
+
+    /// Returns an error naming a column that the file's header lacks, once
+    /// the header has been passed to `select`.
+    pub(crate) fn check_header(&self) -> io::Result<()> {
+        match &self.searcher.fields {
+            Some(fields) if fields.has_header() => check_columns(fields, &self.columns),
+            _ => Ok(()),
+        }
+    }
+
+    /// Replaces the text of `line` with the fields chosen by `--select`, if
+    /// any.
+    pub(crate) fn project<'a>(&self, line: Match<'a>) -> Match<'a> {
+        let Some(fields) = &self.searcher.fields else {
+            return line;
+        };
+        match fields.project(&line.text, &self.columns) {
+            Some(text) => Match {
+                text: Cow::Owned(text),
+                submatches: Vec::new(),
+                ..line
+            },
+            None => line,
+        }
+    }
+}
+
+/// Returns an error naming a column in `fields` that isn't in `columns`.
+fn check_columns(fields: &Fields, columns: &Columns) -> io::Result<()> {
+    match fields.missing(columns) {
+        Some(name) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("no column named '{name}' in the header"),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Matches found in a whole buffer, handed out to the lines they cover.
+struct Spans {
+    /// Every match, from first to last.
+    found: Vec<Range<usize>>,
+    /// The first match that doesn't end before the current line.
+    next: usize,
+}
+
+impl Spans {
+    /// Records in `line`'s submatches the parts of any matches that fall on
+    /// it, and returns whether there were any. Lines must be passed in order.
+    fn cover(&mut self, line: &mut Match, contents: &str) -> bool {
+        let start = line.byte_range.start;
+        let text_end = line.byte_range.end;
+        // A match that ends with the line terminator still touches the line.
+        let rest = &contents[text_end..];
+        let end = text_end
+            + if rest.starts_with("\r\n") {
+                2
+            } else {
+                usize::from(rest.starts_with(['\n', '\r']))
+            };
+
+        let mut covered = false;
+        let mut index = self.next;
+
+        while let Some(found) = self.found.get(index) {
+            if found.start >= end {
+                break;
+            }
+
+            covered = true;
+            let from = found.start.max(start);
+            let to = found.end.min(text_end).max(from);
+            if from < to || found.is_empty() {
+                line.submatches.push(from - start..to - start);
+            }
+
+            // A match that carries on past this line is needed for the next.
+            if found.end > end {
+                break;
+            }
+            index += 1;
+        }
+
+        self.next = index;
+        covered
+    }
+}
+
+/// The selected lines of a `Read` source, returned by
+/// `Searcher::search_reader` and `Searcher::search_path`.
+///
+/// Each line owns its text, since the buffer it was read into is reused.
+pub struct Matches<'s, R> {
+    selector: Selector<'s>,
+    encoding: Option<Encoding>,
+    /// The reader, until the first line is asked for and its start can be
+    /// checked for a BOM and for being binary.
+    reader: Option<BufReader<R>>,
+    /// The lines of the reader, once it has been checked, or `None` if it is
+    /// binary.
+    lines: Option<LineReader<Decoded<BufReader<R>>>>,
+}
+
+impl<R: Read> Matches<'_, R> {
+    fn start(&mut self, reader: BufReader<R>) -> io::Result<()> {
+        let mut reader = encoding::decode(reader, self.encoding)?;
+        if !walk::is_binary(reader.fill_buf()?) {
+            self.lines = Some(LineReader::new(reader));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for Matches<'_, R> {
+    type Item = io::Result<Match<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(reader) = self.reader.take() {
+            if let Err(err) = self.start(reader) {
+                return Some(Err(err));
+            }
+        }
+        let lines = self.lines.as_mut()?;
+
+        loop {
+            match lines.next_line() {
+                Ok(Some(mut line)) => {
+                    let selected = self.selector.select(&mut line);
+                    if line.line_number == 1 {
+                        if let Err(err) = self.selector.check_header() {
+                            return Some(Err(err));
+                        }
+                    }
+                    if selected {
+                        return Some(Ok(line.into_owned()));
+                    }
+                }
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn searches_readers_like_strings() {
+        let config = Config::builder()
+            .pattern("rust")
+            .ignore_case(true)
+            .invert_match(true)
+            .build()
+            .unwrap();
+        let searcher = Searcher::new(&config).unwrap();
+        let contents = "Rust:\nsafe, fast, productive.\nPick three.\nTrust me.";
+
+        let from_str: Vec<Match> = searcher.search(contents).map(Match::into_owned).collect();
+        let from_reader: Vec<Match> = searcher
+            .search_reader(contents.as_bytes())
+            .collect::<io::Result<_>>()
+            .unwrap();
+
+        assert_eq!(from_str, from_reader);
+        assert_eq!(
+            vec!["safe, fast, productive.", "Pick three."],
+            from_reader
+                .iter()
+                .map(|line| &line.text)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn readers_are_decoded_like_files() {
+        let config = Config::builder()
+            .pattern("café")
+            .encoding(Encoding::Latin1)
+            .build()
+            .unwrap();
+        let searcher = Searcher::new(&config).unwrap();
+        let texts = |input: &[u8]| -> Vec<String> {
+            searcher
+                .search_reader(input)
+                .map(|line| line.unwrap().text.into_owned())
+                .collect()
+        };
+
+        assert_eq!(vec!["un caf\u{E9}"], texts(b"th\xE9\nun caf\xE9\n"));
+        // Binary input has no lines selected.
+        assert!(texts(b"caf\xE9\x00").is_empty());
+
+        let auto = Searcher::new(&Config::builder().pattern("café").build().unwrap()).unwrap();
+        let mut utf16 = b"\xFF\xFE".to_vec();
+        utf16.extend("un café".encode_utf16().flat_map(u16::to_le_bytes));
+        let found: Vec<String> = auto
+            .search_reader(&utf16[..])
+            .map(|line| line.unwrap().text.into_owned())
+            .collect();
+        assert_eq!(vec!["un café"], found);
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn multiline_matches_cover_every_line_they_touch() {
+        let config = Config::builder()
+            .pattern(r"fast,\s+\w+|three")
+            .regex(true)
+            .multiline(true)
+            .build()
+            .unwrap();
+        let searcher = Searcher::new(&config).unwrap();
+        let contents = "Rust:\nsafe, fast,\r\nproductive.\nPick three.\nTrust me.";
+
+        let found: Vec<(usize, Vec<Range<usize>>)> = searcher
+            .search(contents)
+            .map(|line| (line.line_number, line.submatches))
+            .collect();
+        assert_eq!(
+            vec![(2, vec![6..11]), (3, vec![0..10]), (4, vec![5..10])],
+            found
+        );
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn multiline_anchors_match_before_crlf() {
+        let config = Config::builder()
+            .pattern("foo$")
+            .regex(true)
+            .multiline(true)
+            .build()
+            .unwrap();
+        let searcher = Searcher::new(&config).unwrap();
+
+        let found: Vec<(usize, Vec<Range<usize>>)> = searcher
+            .search("foo\r\nbar\r\n")
+            .map(|line| (line.line_number, line.submatches))
+            .collect();
+        assert_eq!(vec![(1, vec![0..3])], found);
+    }
+
+    #[test]
+    fn whole_buffers_are_only_searched_for_plain_literals() {
+        let query = |builder: crate::ConfigBuilder| {
+            let searcher = Searcher::new(&builder.build().unwrap()).unwrap();
+            searcher.whole_buffer_query().map(String::from)
+        };
+        let frog = || Config::builder().pattern("frog");
+
+        assert_eq!(Some(String::from("frog")), query(frog()));
+        assert_eq!(None, query(frog().ignore_case(true)));
+        assert_eq!(None, query(frog().word_regexp(true)));
+        assert_eq!(None, query(frog().invert_match(true)));
+        assert_eq!(None, query(frog().regex(true)));
+        assert_eq!(None, query(frog().pattern("toad")));
+    }
+
+    #[test]
+    fn fields_are_replaced_within() {
+        let config = Config::builder()
+            .pattern(r"(\w+)$")
+            .regex(true)
+            .field("name")
+            .replace("<$1>")
+            .build()
+            .unwrap();
+        let searcher = Searcher::new(&config).unwrap();
+
+        let replaced: Vec<String> = searcher
+            .search("name,note\nfrog,pond\n\"toad\",garden\n")
+            .map(|line| searcher.replace(line).text.into_owned())
+            .collect();
+        assert_eq!(vec!["<frog>,pond", "\"<toad>\",garden"], replaced);
+
+        assert!(searcher.check_header("name,note").is_ok());
+        assert!(searcher.check_header("note").is_err());
+    }
+
+    #[test]
+    fn searcher_can_be_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Config>();
+        assert_send_sync::<Searcher>();
+    }
+}
@@ -0,0 +1,195 @@
+//! Drawing the `--interactive` browser and reading keys, using crossterm.
+//!
+//! The browser is drawn on stderr, leaving stdout for the `path:line` that is
+//! picked, so that it can be used as `vim $(minigrep --interactive ...)`.
+//! Like other full-screen programs it uses the terminal's alternate screen,
+//! which leaves the shell's scrollback as it was when it quits.
+
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use crate::browser::{Action, Browser, Key, Row};
+
+/// Shows `browser` until a hit is picked, returning its `path:line`, or the
+/// user quits, returning `None`.
+pub fn run(mut browser: Browser) -> io::Result<Option<String>> {
+    let mut out = BufWriter::new(io::stderr());
+
+    terminal::enable_raw_mode()?;
+    execute!(out, EnterAlternateScreen, Hide)?;
+
+    let picked = browse(&mut browser, &mut out);
+
+    // Put the terminal back even if browsing failed.
+    let restored =
+        execute!(out, Show, LeaveAlternateScreen).and_then(|()| terminal::disable_raw_mode());
+    let picked = picked?;
+    restored?;
+    Ok(picked)
+}
+
+fn browse(browser: &mut Browser, out: &mut impl Write) -> io::Result<Option<String>> {
+    loop {
+        draw(browser, out)?;
+
+        let Event::Key(event) = event::read()? else {
+            // Anything else, such as the terminal being resized, only needs
+            // a redraw.
+            continue;
+        };
+        match key(event).map(|key| browser.handle(key)) {
+            Some(Action::Quit) => return Ok(None),
+            Some(Action::Open(location)) => return Ok(Some(location)),
+            Some(Action::Continue) | None => {}
+        }
+    }
+}
+
+/// Translates a key press into one the browser understands.
+fn key(event: KeyEvent) -> Option<Key> {
+    if event.kind != KeyEventKind::Press {
+        return None;
+    }
+    let control = event.modifiers.contains(KeyModifiers::CONTROL);
+
+    Some(match event.code {
+        KeyCode::Char('c') if control => Key::Escape,
+        KeyCode::Char('p') if control => Key::Up,
+        KeyCode::Char('n') if control => Key::Down,
+        KeyCode::Char(c) if !control => Key::Char(c),
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Esc => Key::Escape,
+        _ => return None,
+    })
+}
+
+/// Draws the prompt on the top line, then the list of hits, then a preview
+/// of the selected hit's file, each taking half of what's left.
+fn draw(browser: &mut Browser, out: &mut impl Write) -> io::Result<()> {
+    let (width, height) = terminal::size()?;
+    let (width, height) = (usize::from(width), usize::from(height));
+    let list_height = height.saturating_sub(2) / 2;
+    let preview_height = height.saturating_sub(2 + list_height);
+    browser.set_page(list_height);
+
+    queue!(out, Clear(ClearType::All))?;
+
+    let (showing, total) = browser.counts();
+    let counts = format!("{showing}/{total}");
+    let prompt = format!("> {}", browser.filter());
+    queue!(
+        out,
+        MoveTo(0, 0),
+        Print(fit(&prompt, width.saturating_sub(counts.len() + 1))),
+        MoveTo(width.saturating_sub(counts.len()) as u16, 0),
+        SetAttribute(Attribute::Dim),
+        Print(counts),
+        SetAttribute(Attribute::Reset),
+    )?;
+
+    for (y, row) in browser.rows(list_height).into_iter().enumerate() {
+        queue!(out, MoveTo(0, (y + 1) as u16))?;
+        match row {
+            Row::File(file) => queue!(
+                out,
+                SetForegroundColor(Color::Magenta),
+                Print(fit(&file.path.display().to_string(), width)),
+                SetForegroundColor(Color::Reset),
+            )?,
+            Row::Hit {
+                line_number,
+                text,
+                submatches,
+                selected,
+            } => {
+                if selected {
+                    queue!(out, SetAttribute(Attribute::Reverse))?;
+                }
+                let number = format!("{line_number:>6}: ");
+                queue!(out, SetForegroundColor(Color::Green), Print(&number))?;
+                queue!(out, SetForegroundColor(Color::Reset))?;
+                highlight(out, text, submatches, width.saturating_sub(number.len()))?;
+                queue!(out, SetAttribute(Attribute::Reset))?;
+            }
+        }
+    }
+
+    let title = browser
+        .previewed()
+        .map(|file| format!("── {} ", file.path.display()))
+        .unwrap_or_default();
+    queue!(
+        out,
+        MoveTo(0, (list_height + 1) as u16),
+        SetAttribute(Attribute::Dim),
+        Print(fit(&format!("{title}{}", "─".repeat(width)), width)),
+        SetAttribute(Attribute::Reset),
+    )?;
+
+    for (y, line) in browser.preview(preview_height).into_iter().enumerate() {
+        let text = format!("{:>6}  {}", line.line_number, line.text);
+        queue!(out, MoveTo(0, (list_height + 2 + y) as u16))?;
+        if line.selected {
+            queue!(out, SetAttribute(Attribute::Bold))?;
+        }
+        queue!(
+            out,
+            Print(fit(&text, width)),
+            SetAttribute(Attribute::Reset)
+        )?;
+    }
+
+    out.flush()
+}
+
+/// Prints the first `width` characters of `text`, with the parts inside
+/// `submatches` in red.
+fn highlight(
+    out: &mut impl Write,
+    text: &str,
+    submatches: &[Range<usize>],
+    width: usize,
+) -> io::Result<()> {
+    let mut segment = String::new();
+    let mut in_match = false;
+
+    for (index, c) in text.char_indices().take(width) {
+        let inside = submatches.iter().any(|found| found.contains(&index));
+        if inside != in_match {
+            queue!(out, Print(&segment))?;
+            segment.clear();
+            let color = if inside { Color::Red } else { Color::Reset };
+            queue!(out, SetForegroundColor(color))?;
+            in_match = inside;
+        }
+        segment.push(printable(c));
+    }
+
+    queue!(out, Print(segment), SetForegroundColor(Color::Reset))
+}
+
+/// Cuts `text` down to `width` characters, so that it fits on one line.
+fn fit(text: &str, width: usize) -> String {
+    text.chars().take(width).map(printable).collect()
+}
+
+/// Replaces tabs and other control characters, which would move the cursor,
+/// with spaces.
+fn printable(c: char) -> char {
+    if c.is_control() {
+        ' '
+    } else {
+        c
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+
+use ignore::overrides::OverrideBuilder;
+use ignore::types::TypesBuilder;
+use ignore::WalkBuilder;
+
+use crate::{edit, Config};
+
+/// An error encountered while walking, which names the path that caused it.
+pub use ignore::Error as WalkError;
+
+/// How many leading bytes of a file are checked for NUL bytes before it is
+/// treated as binary. This is the same heuristic GNU grep uses.
+const BINARY_SNIFF_LEN: usize = 8192;
+
+/// Expands `paths` into the list of files to search.
+///
+/// Files named directly are always searched. Directories are walked
+/// recursively, with entries visited in sorted order so output is stable.
+/// Unless the config says otherwise, the walk skips hidden files and anything
+/// matched by `.gitignore` or `.ignore` files, then applies the config's
+/// `--glob` and `--type` filters. With `--in-place`, it also skips the
+/// backups and temporary files that editing leaves behind.
+///
+/// Fails if one of the globs or file types in `config` is invalid.
+pub fn files(
+    paths: &[PathBuf],
+    config: &Config,
+) -> Result<Vec<Result<PathBuf, WalkError>>, WalkError> {
+    let mut results = Vec::new();
+
+    for path in paths {
+        // Anything that isn't a directory, including the `-` for standard
+        // input, is searched as named without consulting any filters.
+        if !path.is_dir() {
+            results.push(Ok(path.clone()));
+            continue;
+        }
+
+        let walker = builder(path, config)?.build();
+        for entry in walker {
+            match entry {
+                Ok(entry) if entry.file_type().is_some_and(|kind| kind.is_dir()) => {}
+                Ok(entry) if config.in_place && edit::is_leftover(entry.path()) => {}
+                Ok(entry) => results.push(Ok(entry.into_path())),
+                Err(err) => results.push(Err(err)),
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+fn builder(path: &Path, config: &Config) -> Result<WalkBuilder, WalkError> {
+    let mut overrides = OverrideBuilder::new(path);
+    for glob in &config.globs {
+        overrides.add(glob)?;
+    }
+
+    let mut types = TypesBuilder::new();
+    types.add_defaults();
+    for name in &config.types {
+        types.select(name);
+    }
+    for name in &config.types_not {
+        types.negate(name);
+    }
+
+    let respect_ignores = !config.no_ignore;
+
+    let mut builder = WalkBuilder::new(path);
+    builder
+        .hidden(!config.hidden)
+        .ignore(respect_ignores)
+        .git_ignore(respect_ignores)
+        .git_global(respect_ignores)
+        .git_exclude(respect_ignores)
+        .parents(respect_ignores)
+        // Honour .gitignore files even outside a git repository.
+        .require_git(false)
+        .overrides(overrides.build()?)
+        .types(types.build()?)
+        .sort_by_file_name(|a, b| a.cmp(b));
+
+    // A matching --type or --glob takes precedence over the walker's own
+    // hidden check, so hidden entries are also filtered out here.
+    if !config.hidden {
+        builder.filter_entry(|entry| {
+            entry.depth() == 0 || !entry.file_name().as_encoded_bytes().starts_with(b".")
+        });
+    }
+
+    Ok(builder)
+}
+
+/// Returns `true` if `contents` looks like a binary file rather than text.
+pub fn is_binary(contents: &[u8]) -> bool {
+    let len = contents.len().min(BINARY_SNIFF_LEN);
+    contents[..len].contains(&0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn walk(root: &Path, args: &[&str]) -> Vec<PathBuf> {
+        let mut args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        args.insert(0, String::from("minigrep"));
+        args.insert(1, String::from("query"));
+        let config = Config::build_without_file(&args).unwrap();
+
+        files(&[root.to_path_buf()], &config)
+            .unwrap()
+            .into_iter()
+            .map(|file| file.unwrap().strip_prefix(root).unwrap().to_path_buf())
+            .collect()
+    }
+
+    #[test]
+    fn walks_directories_in_sorted_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join(".hidden.rs"), "").unwrap();
+        fs::write(root.join("target/debug/built.rs"), "").unwrap();
+        fs::write(root.join("b/inner.txt"), "").unwrap();
+        fs::write(root.join("a.rs"), "").unwrap();
+        fs::write(root.join("c.txt"), "").unwrap();
+
+        let default = walk(root, &[]);
+        let rust = walk(root, &["--type", "rust"]);
+        let not_txt = walk(root, &["-g", "!*.txt"]);
+        let everything = walk(root, &["--hidden", "--no-ignore", "-T", "txt"]);
+
+        let paths = |paths: &[&str]| -> Vec<PathBuf> { paths.iter().map(PathBuf::from).collect() };
+        assert_eq!(paths(&["a.rs", "b/inner.txt", "c.txt"]), default);
+        assert_eq!(paths(&["a.rs"]), rust);
+        assert_eq!(paths(&["a.rs"]), not_txt);
+        assert_eq!(
+            paths(&[".gitignore", ".hidden.rs", "a.rs", "target/debug/built.rs"]),
+            everything
+        );
+    }
+
+    #[test]
+    fn binary_detection() {
+        assert!(!is_binary(b"Rust:\nsafe, fast, productive.\n"));
+        assert!(is_binary(b"\x7fELF\x02\x01\x01\x00\x00"));
+    }
+}
@@ -0,0 +1,35 @@
+//! Helpers shared by the integration tests, each of which is compiled as a
+//! crate of its own and uses only some of them.
+#![allow(dead_code)]
+
+use std::path::Path;
+use std::process::Command;
+
+/// Where the example files, such as poem.txt, are.
+pub const CRATE_DIR: &str = env!("CARGO_MANIFEST_DIR");
+
+/// Returns a command that runs minigrep in `dir`, without a config file or
+/// `IGNORE_CASE`, so that the environment the tests run in doesn't matter.
+pub fn command(dir: impl AsRef<Path>) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_minigrep"));
+    command
+        .current_dir(dir)
+        .env_remove("IGNORE_CASE")
+        .env("MINIGREP_CONFIG", "");
+    command
+}
+
+/// Runs `command`, returning its exit status and what it printed.
+pub fn output(command: &mut Command) -> (Option<i32>, String) {
+    let output = command.output().unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+/// Runs minigrep in `dir` with `args`, returning its exit status and what it
+/// printed.
+pub fn minigrep(dir: impl AsRef<Path>, args: &[&str]) -> (Option<i32>, String) {
+    output(command(dir).args(args))
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use std::io::Write;
+
+use common::CRATE_DIR;
+
+/// Runs minigrep on poem.txt with a config file containing `config`, and
+/// with `IGNORE_CASE` set if `ignore_case` is.
+fn minigrep(config: &str, ignore_case: bool, args: &[&str]) -> (Option<i32>, String) {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(config.as_bytes()).unwrap();
+
+    let mut command = common::command(CRATE_DIR);
+    command
+        .args(args)
+        .arg("poem.txt")
+        .env("MINIGREP_CONFIG", file.path());
+    if ignore_case {
+        command.env("IGNORE_CASE", "1");
+    }
+    common::output(&mut command)
+}
+
+#[test]
+fn config_file_sets_defaults() {
+    let config = "# Count instead of printing lines.\n--count\n";
+    assert_eq!(
+        (Some(0), String::from("2\n")),
+        minigrep(config, false, &["nobody"])
+    );
+}
+
+#[test]
+fn environment_overrides_config_file() {
+    let config = "--count\n--case-sensitive\n";
+    assert_eq!(
+        (Some(0), String::from("2\n")),
+        minigrep(config, true, &["NOBODY"])
+    );
+}
+
+#[test]
+fn command_line_overrides_environment() {
+    let config = "--count\n";
+    assert_eq!(
+        (Some(1), String::from("0\n")),
+        minigrep(config, true, &["-s", "NOBODY"])
+    );
+}
+
+#[test]
+fn missing_config_file_is_an_error() {
+    let output = common::command(CRATE_DIR)
+        .args(["frog", "poem.txt"])
+        .env("MINIGREP_CONFIG", "no-such-config")
+        .output()
+        .unwrap();
+
+    assert_eq!(Some(2), output.status.code());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("in config file no-such-config: "),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn command_line_turns_off_config_file_switches() {
+    let config = "--line-number\n";
+    assert_eq!(
+        (Some(0), String::from("1:I'm nobody! Who are you?\n")),
+        minigrep(config, false, &["Who"])
+    );
+    assert_eq!(
+        (Some(0), String::from("I'm nobody! Who are you?\n")),
+        minigrep(config, false, &["--no-line-number", "Who"])
+    );
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::CRATE_DIR;
+
+fn minigrep(args: &[&str]) -> (Option<i32>, String) {
+    common::minigrep(CRATE_DIR, args)
+}
+
+#[test]
+fn selected_lines_exit_zero() {
+    assert_eq!(Some(0), minigrep(&["frog", "poem.txt"]).0);
+}
+
+#[test]
+fn no_selected_lines_exit_one() {
+    assert_eq!(Some(1), minigrep(&["toad", "poem.txt"]).0);
+}
+
+#[test]
+fn errors_exit_two() {
+    assert_eq!(Some(2), minigrep(&["frog", "no-such-file.txt"]).0);
+    assert_eq!(Some(2), minigrep(&["--frog", "poem.txt"]).0);
+}
+
+#[test]
+fn quiet_match_wins_over_errors() {
+    let (status, stdout) = minigrep(&["-q", "frog", "poem.txt", "no-such-file.txt"]);
+
+    assert_eq!(Some(0), status);
+    assert_eq!("", stdout);
+}
+
+#[test]
+fn files_without_match() {
+    let (status, stdout) = minigrep(&["-L", "frog", "poem.txt", "output.txt"]);
+
+    assert_eq!(Some(0), status);
+    assert_eq!("output.txt\n", stdout);
+}
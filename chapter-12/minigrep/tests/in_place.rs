@@ -0,0 +1,35 @@
+mod common;
+
+use std::fs;
+
+use common::minigrep;
+
+#[test]
+fn editing_a_directory_twice_keeps_both_backups() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    fs::write(dir.join("pond.txt"), "a frog\n").unwrap();
+
+    let (first, _) = minigrep(dir, &["frog", ".", "--replace", "toad", "--in-place"]);
+    // The backup from the first run contains "frog" too, but isn't edited.
+    let (second, _) = minigrep(
+        dir,
+        &["-E", "frog|toad", ".", "--replace", "newt", "--in-place"],
+    );
+
+    let read = |name: &str| fs::read_to_string(dir.join(name)).ok();
+    let pond = read("pond.txt");
+    let backup = read("pond.txt.bak");
+    let second_backup = read("pond.txt.bak.1");
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+
+    assert_eq!((Some(0), Some(0)), (first, second));
+    assert_eq!(Some(String::from("a newt\n")), pond);
+    assert_eq!(Some(String::from("a frog\n")), backup);
+    assert_eq!(Some(String::from("a toad\n")), second_backup);
+    assert_eq!(vec!["pond.txt", "pond.txt.bak", "pond.txt.bak.1"], names);
+}